@@ -36,11 +36,14 @@ impl SimdInput {
     /// padding in the number of 128 vectors
     pub(crate) fn new_with_padding(ptr: &[__m128], tail: &[u8]) -> Self {
         let load = ptr.len();
-        println!("---------------------------------------------------------------------------------");
-        println!("Last iteration");
-        println!("Number of vectors to load {}", load);
-        println!("The tail len: {}", tail.len());
-        println!("---------------------------------------------------------------------------------\n");
+        #[cfg(feature = "trace")]
+        {
+            println!("---------------------------------------------------------------------------------");
+            println!("Last iteration");
+            println!("Number of vectors to load {}", load);
+            println!("The tail len: {}", tail.len());
+            println!("---------------------------------------------------------------------------------\n");
+        }
 
         assert!(
             load < 4,
@@ -56,6 +59,7 @@ impl SimdInput {
             .enumerate()
             .for_each(|(i, value_u8)| padded_tail[i] = *value_u8);
 
+        #[cfg(feature = "trace")]
         println!("The now padded tail: {:?}", &padded_tail);
 
         unsafe {
@@ -94,9 +98,7 @@ impl SimdInput {
     }
 }
 
-use crate::high_nibble_mask;
-use crate::low_nibble_mask;
-use crate::stage1::Stage1;
+use crate::stage1::{Classifier, Stage1};
 
 #[macro_export]
 macro_rules! set1_epi8 {
@@ -122,7 +124,7 @@ fn get_struct_positions(
         let struct2 = _mm_and_si128(res2, struct_mask);
         let struct3 = _mm_and_si128(res3, struct_mask);
 
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "trace")]
         {
             // show result for v0-3
             println!("----------------------------------------------------------------------------------");
@@ -190,52 +192,49 @@ fn get_struct_positions(
 //
 impl Stage1<__m128i> for SimdInput {
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
-    fn structure(&self, structure: &mut u64, in_string: &mut i64) {
+    fn structure(
+        &self,
+        classifier: &Classifier,
+        structure: &mut u64,
+        in_string: &mut i64,
+    ) {
         // the bit-set lookup tables for csv-related structure
         unsafe {
-            // constant vectors
-            let lo_nibble_mask: __m128i = low_nibble_mask!();
-            let hi_nibble_mask: __m128i = high_nibble_mask!();
+            // lookup vectors computed for the dialect
+            let lo_nibble_mask: __m128i = classifier.lo;
+            let hi_nibble_mask: __m128i = classifier.hi;
 
-            #[cfg(debug_assertions)]
+            #[cfg(feature = "trace")]
             {
                 // 🧮 debug: extract 64-bits
                 println!("🧮 v0-3 bit-set lookup of csv structure");
                 let tmp00 = _mm_extract_epi64(self.v0, 0);
                 let tmp00 = tmp00.to_le_bytes();
-                let tmp00 =
-                    std::str::from_utf8(&tmp00).expect("Fond invalid UTF-8");
+                let tmp00 = String::from_utf8_lossy(&tmp00);
                 let tmp01 = _mm_extract_epi64(self.v0, 1);
                 let tmp01 = tmp01.to_le_bytes();
-                let tmp01 =
-                    std::str::from_utf8(&tmp01).expect("Fond invalid UTF-8");
+                let tmp01 = String::from_utf8_lossy(&tmp01);
 
                 let tmp10 = _mm_extract_epi64(self.v1, 0);
                 let tmp10 = tmp10.to_le_bytes();
-                let tmp10 =
-                    std::str::from_utf8(&tmp10).expect("Fond invalid UTF-8");
+                let tmp10 = String::from_utf8_lossy(&tmp10);
                 let tmp11 = _mm_extract_epi64(self.v1, 1);
                 let tmp11 = tmp11.to_le_bytes();
-                let tmp11 =
-                    std::str::from_utf8(&tmp11).expect("Fond invalid UTF-8");
+                let tmp11 = String::from_utf8_lossy(&tmp11);
 
                 let tmp20 = _mm_extract_epi64(self.v2, 0);
                 let tmp20 = tmp20.to_le_bytes();
-                let tmp20 =
-                    std::str::from_utf8(&tmp20).expect("Fond invalid UTF-8");
+                let tmp20 = String::from_utf8_lossy(&tmp20);
                 let tmp21 = _mm_extract_epi64(self.v2, 1);
                 let tmp21 = tmp21.to_le_bytes();
-                let tmp21 =
-                    std::str::from_utf8(&tmp21).expect("Fond invalid UTF-8");
+                let tmp21 = String::from_utf8_lossy(&tmp21);
 
                 let tmp30 = _mm_extract_epi64(self.v3, 0);
                 let tmp30 = tmp30.to_le_bytes();
-                let tmp30 =
-                    std::str::from_utf8(&tmp30).expect("Fond invalid UTF-8");
+                let tmp30 = String::from_utf8_lossy(&tmp30);
                 let tmp31 = _mm_extract_epi64(self.v3, 1);
                 let tmp31 = tmp31.to_le_bytes();
-                let tmp31 =
-                    std::str::from_utf8(&tmp31).expect("Fond invalid UTF-8");
+                let tmp31 = String::from_utf8_lossy(&tmp31);
                 println!(
                     "{}{}{}{}{}{}{}{}",
                     tmp00, tmp01, tmp10, tmp11, tmp20, tmp21, tmp30, tmp31
@@ -263,7 +262,7 @@ impl Stage1<__m128i> for SimdInput {
             let nib_hi2 = _mm_srli_epi64(self.v2, 4);
             let nib_hi3 = _mm_srli_epi64(self.v3, 4);
 
-            #[cfg(debug_assertions)]
+            #[cfg(feature = "trace")]
             {
                 // input
                 println!("🚧 lo nibble (self.0, 0)");
@@ -315,7 +314,7 @@ impl Stage1<__m128i> for SimdInput {
             let res2 = _mm_and_si128(shuf_lo2, shuf_hi2);
             let res3 = _mm_and_si128(shuf_lo3, shuf_hi3);
 
-            #[cfg(debug_assertions)]
+            #[cfg(feature = "trace")]
             {
                 // show all structure for v0-3
                 println!("----------------------------------------------------------------------------------");
@@ -406,7 +405,7 @@ impl Stage1<__m128i> for SimdInput {
             *structure = _mm_cvtsi128_si64(result) as u64;
             *in_string = _mm_cvtsi128_si64(string_mask) as i64 >> 63;
 
-            #[cfg(debug_assertions)]
+            #[cfg(feature = "trace")]
             {
                 println!("----------------------------------------------------------------------------------");
                 println!("👉 structure result WIP");
@@ -435,39 +434,31 @@ impl Stage1<__m128i> for SimdInput {
             // show the string representation the data
             let tmp00 = _mm_extract_epi64(self.v0, 0);
             let tmp00 = tmp00.to_le_bytes();
-            let tmp00 =
-                std::str::from_utf8(&tmp00).expect("Found invalid UTF-8");
+            let tmp00 = String::from_utf8_lossy(&tmp00);
             let tmp01 = _mm_extract_epi64(self.v0, 1);
             let tmp01 = tmp01.to_le_bytes();
-            let tmp01 =
-                std::str::from_utf8(&tmp01).expect("Found invalid UTF-8");
+            let tmp01 = String::from_utf8_lossy(&tmp01);
 
             let tmp10 = _mm_extract_epi64(self.v1, 0);
             let tmp10 = tmp10.to_le_bytes();
-            let tmp10 =
-                std::str::from_utf8(&tmp10).expect("Found invalid UTF-8");
+            let tmp10 = String::from_utf8_lossy(&tmp10);
             let tmp11 = _mm_extract_epi64(self.v1, 1);
             let tmp11 = tmp11.to_le_bytes();
-            let tmp11 =
-                std::str::from_utf8(&tmp11).expect("Found invalid UTF-8");
+            let tmp11 = String::from_utf8_lossy(&tmp11);
 
             let tmp20 = _mm_extract_epi64(self.v2, 0);
             let tmp20 = tmp20.to_le_bytes();
-            let tmp20 =
-                std::str::from_utf8(&tmp20).expect("Found invalid UTF-8");
+            let tmp20 = String::from_utf8_lossy(&tmp20);
             let tmp21 = _mm_extract_epi64(self.v2, 1);
             let tmp21 = tmp21.to_le_bytes();
-            let tmp21 =
-                std::str::from_utf8(&tmp21).expect("Found invalid UTF-8");
+            let tmp21 = String::from_utf8_lossy(&tmp21);
 
             let tmp30 = _mm_extract_epi64(self.v3, 0);
             let tmp30 = tmp30.to_le_bytes();
-            let tmp30 =
-                std::str::from_utf8(&tmp30).expect("Found invalid UTF-8");
+            let tmp30 = String::from_utf8_lossy(&tmp30);
             let tmp31 = _mm_extract_epi64(self.v3, 1);
             let tmp31 = tmp31.to_le_bytes();
-            let tmp31 =
-                std::str::from_utf8(&tmp31).expect("Found invalid UTF-8");
+            let tmp31 = String::from_utf8_lossy(&tmp31);
 
             println!(
                 "{}{}{}{}{}{}{}{}",
@@ -7,8 +7,96 @@
 //    👉 idiomatic to have the alias = parent
 //    👉 idiomatic struct/enum to use a fully qualified path
 //
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use csv_simd::sniff::PREFIX_LEN;
+use csv_simd::{
+    rust_struct, sniff_details, SampleSpec, Schema, SqlDump, SqlFlavor, Tape,
+};
+
+const USAGE: &str = "usage: csv_simd struct <file.csv> [StructName]
+       csv_simd sql <file.csv> <table> [postgres|sqlite|copy]
+       csv_simd validate <file.csv> <schema.txt|schema.json>";
 
 fn main() {
-    // csv_simd::run().expect("Failed to parse the csv");
-    println!("csv_simd::main - not required");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["struct", path] => print_struct(path, None),
+        ["struct", path, name] => print_struct(path, Some(name)),
+        ["sql", path, table] => print_sql(path, table, "postgres"),
+        ["sql", path, table, format] => print_sql(path, table, format),
+        ["validate", path, schema] => print_validation(path, schema),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// The dialect and the header are sniffed from a prefix; the file is memory mapped
+fn read_tape(path: &str) -> Tape {
+    // more than PREFIX_LEN, so the sniffer can drop a partial last line
+    let mut prefix = Vec::new();
+    File::open(path)
+        .and_then(|file| {
+            file.take(2 * PREFIX_LEN as u64).read_to_end(&mut prefix)
+        })
+        .expect("Failed to read the csv");
+    sniff_details(&prefix)
+        .builder()
+        .build_from_path(path)
+        .expect("Failed to parse the csv")
+}
+
+/// The struct of the records of the file; named after the file by default
+fn print_struct(path: &str, name: Option<&str>) {
+    let tape = read_tape(path);
+    let schema = tape.infer_schema(SampleSpec::All);
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    print!("{}", rust_struct(name.unwrap_or(&stem), &schema));
+}
+
+/// CREATE TABLE, then the records as INSERT statements or COPY (PostgreSQL)
+fn print_sql(path: &str, table: &str, format: &str) {
+    let flavor = match format {
+        "postgres" | "copy" => SqlFlavor::Postgres,
+        "sqlite" => SqlFlavor::Sqlite,
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let tape = read_tape(path);
+    let schema = tape.infer_schema(SampleSpec::All);
+    let dump = SqlDump::new(table, &schema, flavor);
+    print!("{}", dump.create_table());
+    let out = std::io::stdout().lock();
+    match format {
+        "copy" => dump.write_copy(&tape, out),
+        _ => dump.write_inserts(&tape, out),
+    }
+    .expect("Failed to write the records");
+}
+
+/// The report as JSON; exits with 1 when a check failed
+fn print_validation(path: &str, schema_path: &str) {
+    let text = std::fs::read_to_string(schema_path)
+        .expect("Failed to read the schema");
+    let schema = match schema_path.ends_with(".json") {
+        true => Schema::from_json(&text),
+        false => Schema::from_text(&text),
+    }
+    .expect("Failed to parse the schema");
+    let tape = read_tape(path);
+    let report = tape.validate(&schema).expect("Failed to validate");
+    let json = serde_json::to_string_pretty(&report).expect("a report");
+    println!("{}", json);
+    if !report.is_valid() {
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,558 @@
+///
+/// The builder of a Tape
+/// https://rust-unofficial.github.io/patterns/patterns/builder.html
+///
+/// The options describe the data (dialect, comment, header, encoding), and how to index the data
+/// (index width, validation, backend).  The terminal methods host the data:
+///
+/// * `build_from_path`: memory map (or copy, see `Protection`) a file
+/// * `build_from_bytes`: bytes already in memory
+/// * `build_from_reader`: read to the end of a `Read`
+///
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use memmap::Mmap;
+
+use crate::dialect::{Dialect, Escape, Trim};
+use crate::encoding::{Encoding, UTF8_BOM};
+use crate::error::StructureError;
+use crate::guard::{FileLock, Protection, SourceStamp};
+use crate::nulls::NullValues;
+use crate::number_format::NumberFormat;
+use crate::reader;
+use crate::scalar;
+use crate::tape::{DataBytes, Header, Tape, TapeCore};
+
+/// Whether the first line hosts the field names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum HeaderMode {
+    #[default]
+    Present,
+    /// The field names are generated: `field_0`, `field_1`, ...
+    ///
+    /// ⚠️  The Tape requires a header; the generated header is prepended to a copy of the data.
+    Absent,
+}
+
+/// The width of the values in the `StructureIndex`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum IndexWidth {
+    #[default]
+    Usize,
+    /// Half the memory of usize; the data must be under 4 GiB.
+    ///
+    /// ⚠️  The index is computed as usize, then narrowed; the peak memory is not halved.
+    U32,
+}
+
+/// How much of the structure is checked when the Tape is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum Validation {
+    /// The size of the index is a multiple of the record size
+    #[default]
+    Shape,
+    /// ... and every record ends with a newline (same number of fields as the header)
+    Records,
+    /// ... and the index is the one computed by the reference reader, and UTF-8 data is valid
+    Full,
+}
+
+/// Which reader computes the `StructureIndex`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Simd when the cpu and the dialect allow; otherwise Scalar
+    #[default]
+    Auto,
+    Simd,
+    Scalar,
+}
+
+/// Configure, then build a Tape
+#[derive(Debug, Clone, Default)]
+pub struct TapeBuilder {
+    dialect: Dialect,
+    comment: Option<u8>,
+    skip_lines: u32,
+    header: HeaderMode,
+    encoding: Encoding,
+    number_format: NumberFormat,
+    nulls: NullValues,
+    index_width: IndexWidth,
+    validation: Validation,
+    backend: Backend,
+    protection: Protection,
+}
+
+impl TapeBuilder {
+    pub fn new() -> Self {
+        TapeBuilder::default()
+    }
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.dialect.delimiter = delimiter;
+        self
+    }
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.dialect.quote = quote;
+        self
+    }
+    pub fn escape(mut self, escape: Escape) -> Self {
+        self.dialect.escape = escape;
+        self
+    }
+    pub fn trim(mut self, trim: Trim) -> Self {
+        self.dialect.trim = trim;
+        self
+    }
+    /// Skip the lines that start with the byte.
+    ///
+    /// ⚠️  Only the lines before the header; the index has no place for a line between records.
+    pub fn comment(mut self, comment: u8) -> Self {
+        self.comment = Some(comment);
+        self
+    }
+    /// Skip the first lines (after the BOM); e.g. Excel's `sep=;`
+    pub fn skip_lines(mut self, skip_lines: u32) -> Self {
+        self.skip_lines = skip_lines;
+        self
+    }
+    pub fn header(mut self, header: HeaderMode) -> Self {
+        self.header = header;
+        self
+    }
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+    /// How the numbers are written; see `Tape::set_number_format`
+    pub fn number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+    /// The tokens that mean "missing"; see `Tape::set_null_values`
+    pub fn null_values(mut self, nulls: NullValues) -> Self {
+        self.nulls = nulls;
+        self
+    }
+    pub fn index_width(mut self, index_width: IndexWidth) -> Self {
+        self.index_width = index_width;
+        self
+    }
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+    /// Only applies to `build_from_path`
+    pub fn protection(mut self, protection: Protection) -> Self {
+        self.protection = protection;
+        self
+    }
+
+    /// Host the data of a file; see `Protection`
+    pub fn build_from_path(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Tape, StructureError> {
+        let path = path.as_ref();
+        let (bytes, stamp, lock) = self.host(path, Vec::new())?;
+        Ok(self.build(bytes)?.guarded(stamp, lock))
+    }
+    /// Host bytes already in memory (e.g., `Vec<u8>`, `Mmap`)
+    pub fn build_from_bytes(
+        &self,
+        bytes: impl Into<DataBytes>,
+    ) -> Result<Tape, StructureError> {
+        self.build(bytes.into())
+    }
+    /// Read the data to the end
+    pub fn build_from_reader(
+        &self,
+        mut reader: impl Read,
+    ) -> Result<Tape, StructureError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        self.build(bytes.into())
+    }
+
+    /// Memory map, or read (into the scratch buffer) the file; see `Protection`
+    pub(crate) fn host(
+        &self,
+        path: &Path,
+        mut scratch: Vec<u8>,
+    ) -> Result<(DataBytes, SourceStamp, Option<FileLock>), StructureError>
+    {
+        let mut file = File::open(path)?;
+        let stamp = SourceStamp::capture(path, &file)?;
+
+        match self.protection {
+            Protection::Copy => {
+                scratch.clear();
+                scratch.reserve(stamp.len as usize);
+                file.read_to_end(&mut scratch)?;
+                Ok((scratch.into(), stamp, None))
+            }
+            Protection::Lock => {
+                let memmap = unsafe { Mmap::map(&file)? };
+                let lock = FileLock::acquire(path, file)?;
+                Ok((memmap.into(), stamp, Some(lock)))
+            }
+            _ => Ok((unsafe { Mmap::map(&file)? }.into(), stamp, None)),
+        }
+    }
+    fn build(&self, bytes: DataBytes) -> Result<Tape, StructureError> {
+        self.build_into(bytes, Vec::new())
+    }
+    /// Build with a recycled index accumulator (see `Parser`)
+    pub(crate) fn build_into(
+        &self,
+        bytes: DataBytes,
+        acc: Vec<usize>,
+    ) -> Result<Tape, StructureError> {
+        if self.index_width == IndexWidth::U32
+            && bytes.len() > u32::MAX as usize
+        {
+            return Err(StructureError::InvalidOption {
+                option: "index_width",
+                reason: format!("{} bytes exceed u32", bytes.len()),
+            });
+        }
+        if self.preamble_len(&bytes) == bytes.len() {
+            return Err(StructureError::EmptyInput);
+        }
+        let backend = self.resolve_backend()?;
+        // the index describes the data from the header onward
+        let (bytes, offset) = match self.header {
+            // the preamble is not copied
+            HeaderMode::Absent => {
+                (self.with_generated_header(&bytes).into(), 0)
+            }
+            _ => {
+                let offset = self.preamble_len(&bytes);
+                (bytes, offset)
+            }
+        };
+        // ⚠️  the last record may not end with a newline (network payloads, decompressed
+        //    data); the copy adds the newline of the header
+        let bytes = match bytes.ends_with(b"\n") {
+            true => bytes,
+            false => {
+                let newline = self.terminator(&bytes[offset..]);
+                [&bytes[..], newline].concat().into()
+            }
+        };
+        let data = &bytes[offset..];
+        let mut header =
+            Header::with_dialect(data, self.encoding, self.dialect)
+                .with_preamble(offset);
+        header.set_number_format(self.number_format);
+        header.set_null_values(self.nulls.clone());
+        let mut index = match backend {
+            Backend::Simd => reader::read_into(data, &self.dialect, acc),
+            _ => scalar::read_into(data, &self.dialect, acc),
+        };
+        if self.validation >= Validation::Full && backend == Backend::Simd {
+            reader::verify_with(data, &index, &self.dialect)?;
+        }
+        index.shift(offset);
+        if self.index_width == IndexWidth::U32 {
+            index = index.narrow()?;
+        }
+
+        let tape = Tape::from_core(TapeCore::create(bytes, index, header))?;
+        if self.validation >= Validation::Records {
+            validate_records(&tape)?;
+        }
+        if self.validation >= Validation::Full
+            && self.encoding == Encoding::Utf8
+        {
+            validate_utf8(&tape, offset)?;
+        }
+        Ok(tape)
+    }
+    /// The end of an unterminated last record: CRLF when the header ends with one
+    fn terminator(&self, data: &[u8]) -> &'static [u8] {
+        let crlf = match data.iter().position(|&byte| byte == b'\n') {
+            Some(lf) => lf > 0 && data[lf - 1] == b'\r',
+            None => false,
+        };
+        match (data.ends_with(b"\r"), crlf) {
+            (true, _) => b"\n",
+            (false, true) => b"\r\n",
+            (false, false) => b"\n",
+        }
+    }
+    /// Auto -> Simd | Scalar
+    fn resolve_backend(&self) -> Result<Backend, StructureError> {
+        let simd_escape = self.dialect.escape == Escape::Doubled;
+        match self.backend {
+            Backend::Auto if simd_escape && simd_available() => {
+                Ok(Backend::Simd)
+            }
+            Backend::Auto | Backend::Scalar => Ok(Backend::Scalar),
+            Backend::Simd if !simd_escape => {
+                Err(StructureError::InvalidOption {
+                    option: "backend",
+                    reason: "the simd reader only supports the \"\" escape"
+                        .into(),
+                })
+            }
+            Backend::Simd if !simd_available() => {
+                Err(StructureError::InvalidOption {
+                    option: "backend",
+                    reason: "the cpu does not support ssse3, sse4.1, pclmulqdq"
+                        .into(),
+                })
+            }
+            backend => Ok(backend),
+        }
+    }
+    /// The length of the BOM and the comment lines before the header
+    fn preamble_len(&self, bytes: &[u8]) -> usize {
+        let mut offset = match self.encoding {
+            Encoding::Utf8 if bytes.starts_with(UTF8_BOM) => UTF8_BOM.len(),
+            _ => 0,
+        };
+        let line_len = |offset: usize| {
+            bytes[offset..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(bytes.len() - offset, |end| end + 1)
+        };
+        for _ in 0..self.skip_lines {
+            offset += line_len(offset);
+        }
+        if let Some(comment) = self.comment {
+            while bytes.get(offset) == Some(&comment) {
+                offset += line_len(offset);
+            }
+        }
+        offset
+    }
+    /// Prepend `field_0,field_1,...` to a copy of the data (without the preamble)
+    fn with_generated_header(&self, bytes: &[u8]) -> Vec<u8> {
+        let bytes = &bytes[self.preamble_len(bytes)..];
+        let mut in_quotes = false;
+        let mut field_cnt = 1;
+        let mut new_line: &[u8] = b"\n";
+        for (pos, &byte) in bytes.iter().enumerate() {
+            match byte {
+                b if b == self.dialect.quote => in_quotes = !in_quotes,
+                b if b == self.dialect.delimiter && !in_quotes => {
+                    field_cnt += 1
+                }
+                b'\r' | b'\n' if !in_quotes => {
+                    if bytes[pos..].starts_with(b"\r\n") {
+                        new_line = b"\r\n";
+                    }
+                    break;
+                }
+                _ => (),
+            }
+        }
+        let names = (0..field_cnt)
+            .map(|idx| format!("field_{}", idx))
+            .collect::<Vec<_>>()
+            .join(&(self.dialect.delimiter as char).to_string());
+
+        let mut generated =
+            Vec::with_capacity(names.len() + new_line.len() + bytes.len());
+        generated.extend_from_slice(names.as_bytes());
+        generated.extend_from_slice(new_line);
+        generated.extend_from_slice(bytes);
+        generated
+    }
+}
+
+/// The instructions used by Stage1
+pub(crate) fn simd_available() -> bool {
+    is_x86_feature_detected!("ssse3")
+        && is_x86_feature_detected!("sse4.1")
+        && is_x86_feature_detected!("pclmulqdq")
+}
+
+/// Every record ends with a newline
+fn validate_records(tape: &Tape) -> Result<(), StructureError> {
+    let jump = *tape.record_jump_size;
+    let field_cnt = tape.header.field_cnt as usize;
+    let bytes = tape.bytes();
+
+    (0..tape.record_cnt.saturating_sub(1))
+        .find(|&record| {
+            let key = (record as usize + 1) * jump + field_cnt;
+            !matches!(
+                tape.index().get(key).and_then(|pos| bytes.get(pos)),
+                Some(b'\r') | Some(b'\n')
+            )
+        })
+        .map_or(Ok(()), |record| {
+            Err(StructureError::RaggedRecord { record })
+        })
+}
+
+/// The data bytes are valid UTF-8; the error locates the first invalid byte
+fn validate_utf8(tape: &Tape, offset: usize) -> Result<(), StructureError> {
+    match std::str::from_utf8(&tape.bytes()[offset..]) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let at = offset + e.valid_up_to();
+            let jump = *tape.record_jump_size;
+            // the slot that starts the field hosting the byte
+            let key =
+                tape.index().slots().partition_point(|pos| pos < at).max(1) - 1;
+            Err(StructureError::InvalidUtf8 {
+                record: (key / jump).saturating_sub(1) as u32,
+                field: Some((key % jump) as u32),
+                valid_up_to: e.valid_up_to(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RecordSource, StructureIndex};
+
+    #[test]
+    fn header_only_and_empty() {
+        for data in [&b"a,b"[..], b"a,b\n", b"a,b\r\n"] {
+            let tape = TapeBuilder::new().build_from_bytes(data).unwrap();
+            assert_eq!(tape.header(), &vec!["a".to_string(), "b".into()]);
+            assert_eq!(tape.as_records().len(), 0);
+        }
+        for data in [&b"a,b\n1,2"[..], b"a,b\r\n1,2", b"a,b\r\n1,2\r"] {
+            let tape = TapeBuilder::new().build_from_bytes(data).unwrap();
+            let records = tape.as_records().collect::<Vec<_>>();
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].value(1).unwrap(), "2");
+        }
+        let tape = TapeBuilder::new()
+            .build_from_bytes(&b"a,b\r\n1,2\r\n3,"[..])
+            .unwrap();
+        assert_eq!(tape.as_records().len(), 2);
+        assert_eq!(tape.seek_value(1, 0).unwrap().as_deref(), Some("3"));
+        assert!(matches!(
+            TapeBuilder::new().build_from_bytes(&b""[..]),
+            Err(StructureError::EmptyInput)
+        ));
+        assert!(matches!(
+            TapeBuilder::new().build_from_bytes(&b"\xef\xbb\xbf"[..]),
+            Err(StructureError::EmptyInput)
+        ));
+    }
+    #[test]
+    fn dialect_options() {
+        let bytes =
+            b"# exported\n# by hand\nName;Note\n' a ';'b;c'\nd;'it\\'s'\n";
+        let tape = TapeBuilder::new()
+            .delimiter(b';')
+            .quote(b'\'')
+            .escape(Escape::Backslash(b'\\'))
+            .comment(b'#')
+            .trim(Trim::All)
+            .validation(Validation::Full)
+            .build_from_bytes(bytes.to_vec())
+            .unwrap();
+        assert_eq!(tape.header(), &vec!["Name", "Note"]);
+        let records = tape.as_records().collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].value(0).unwrap(), " a ");
+        assert_eq!(records[0].value(1).unwrap(), "b;c");
+        assert_eq!(records[1].value(1).unwrap(), "it's");
+    }
+    #[test]
+    fn index_width() {
+        let build = |index_width| {
+            TapeBuilder::new()
+                .comment(b'#')
+                .index_width(index_width)
+                .validation(Validation::Full)
+                .build_from_bytes(&b"# preamble\na,b\n1,\"x,y\"\n3,4\n"[..])
+                .unwrap()
+        };
+        let wide = build(IndexWidth::Usize);
+        let narrow = build(IndexWidth::U32);
+        assert_eq!(narrow.index().width(), IndexWidth::U32);
+        assert!(wide.index().iter().eq(narrow.index().iter()));
+        assert_eq!(narrow.seek_value(0, 1).unwrap().as_deref(), Some("x,y"));
+        assert_eq!(narrow.column("b").unwrap().get(1), Some(&b"4"[..]));
+        let record = narrow.as_records().next().unwrap();
+        assert_eq!(record.value(0).as_deref(), Some("1"));
+
+        let too_wide = StructureIndex::Usize(bytemuck::cast_vec(vec![
+            0_usize,
+            u32::MAX as usize + 1,
+        ]));
+        assert!(matches!(
+            too_wide.narrow(),
+            Err(StructureError::InvalidOption {
+                option: "index_width",
+                ..
+            })
+        ));
+    }
+    #[test]
+    fn backends_agree() {
+        let bytes = std::fs::read("./res/sample_rx.csv").unwrap();
+        let build = |backend| {
+            TapeBuilder::new()
+                .backend(backend)
+                .validation(Validation::Full)
+                .build_from_bytes(bytes.clone())
+                .unwrap()
+        };
+        let simd = build(Backend::Simd);
+        let scalar = build(Backend::Scalar);
+        assert_eq!(simd.index(), scalar.index());
+        assert_eq!(simd.header()[0], "NPI Number");
+        assert!(TapeBuilder::new()
+            .backend(Backend::Simd)
+            .escape(Escape::Backslash(b'\\'))
+            .build_from_bytes(bytes)
+            .is_err());
+    }
+    #[test]
+    fn absent_header() {
+        let tape = TapeBuilder::new()
+            .header(HeaderMode::Absent)
+            .build_from_reader(&b"1,\"x,y\"\r\n2,z\r\n"[..])
+            .unwrap();
+        assert_eq!(tape.header(), &vec!["field_0", "field_1"]);
+        assert_eq!(tape.column("field_1").unwrap().value(0).unwrap(), "x,y");
+        assert_eq!((&tape).seek_record(1).unwrap(), Some("2,z"));
+    }
+    #[test]
+    fn validation() {
+        let ragged = b"a,b\n1,2\n3\n4,5,6\n".to_vec();
+        assert!(TapeBuilder::new().build_from_bytes(ragged.clone()).is_ok());
+        assert!(matches!(
+            TapeBuilder::new()
+                .validation(Validation::Records)
+                .build_from_bytes(ragged),
+            Err(StructureError::RaggedRecord { record: 1 })
+        ));
+        assert!(matches!(
+            TapeBuilder::new()
+                .validation(Validation::Full)
+                .build_from_bytes(b"a,b\n1,\xff\n".to_vec()),
+            Err(StructureError::InvalidUtf8 {
+                record: 0,
+                field: Some(1),
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+///
+/// Rust source from a Schema
+///
+/// A struct to deserialize the records into (see `Tape::deserialize`): one field per column,
+/// in the order of the header.
+///
+///   NPI Number (integer)       -> `#[serde(rename = "NPI Number")] pub npi_number: i64`
+///   Year-Month (year-month)    -> `pub year_month: csv_simd::YearMonth`
+///   a nullable column          -> `Option<..>`
+///
+/// 🔑 The output depends on the Schema only; it can be checked in and compared.
+///
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::schema::{ColumnSchema, ColumnType, Schema};
+
+const KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const",
+    "continue", "crate", "do", "dyn", "else", "enum", "extern", "false",
+    "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match",
+    "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self",
+    "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// The source of a struct `name` with a field per column of the schema
+pub fn rust_struct(name: &str, schema: &Schema) -> String {
+    let mut idents = HashSet::new();
+    let mut source = String::new();
+    // infallible: writes to a String
+    let _ = writeln!(source, "use serde::Deserialize;\n");
+    let _ = writeln!(
+        source,
+        "/// {} columns; the types of {} records",
+        schema.columns.len(),
+        schema.sampled
+    );
+    let _ = writeln!(source, "#[derive(Debug, Clone, Deserialize)]");
+    let _ = writeln!(source, "pub struct {} {{", type_ident(name));
+    for (field_idx, column) in schema.columns.iter().enumerate() {
+        let mut ident = field_ident(&column.name, field_idx);
+        if !idents.insert(ident.clone()) {
+            ident = (2..)
+                .map(|n| format!("{}_{}", ident, n))
+                .find(|candidate| !idents.contains(candidate))
+                .expect("an unused suffix");
+            idents.insert(ident.clone());
+        }
+        if ident.trim_start_matches("r#") != column.name {
+            let _ =
+                writeln!(source, "    #[serde(rename = {:?})]", column.name);
+        }
+        let _ = writeln!(source, "    pub {}: {},", ident, rust_type(column));
+    }
+    source.push_str("}\n");
+    source
+}
+
+/// The type of the field
+fn rust_type(column: &ColumnSchema) -> String {
+    let ty = match column.column_type {
+        ColumnType::Integer => "i64",
+        ColumnType::Float => "f64",
+        ColumnType::Boolean => "bool",
+        ColumnType::Date => "csv_simd::Date",
+        ColumnType::YearMonth => "csv_simd::YearMonth",
+        ColumnType::DateTime => "csv_simd::DateTime",
+        ColumnType::Categorical | ColumnType::Text => "String",
+    };
+    match column.nullable {
+        true => format!("Option<{}>", ty),
+        false => ty.to_string(),
+    }
+}
+
+/// The words of a name: runs of ASCII letters and digits
+fn words(name: &str) -> impl Iterator<Item = &str> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+/// "NPI Number" -> npi_number; "2015 total" -> field_2015_total; "type" -> r#type
+fn field_ident(name: &str, field_idx: usize) -> String {
+    let ident = words(name)
+        .map(|word| word.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("_");
+    match ident.chars().next() {
+        None => format!("field_{}", field_idx),
+        Some(first) if first.is_ascii_digit() => format!("field_{}", ident),
+        _ if KEYWORDS.contains(&ident.as_str()) => match ident.as_str() {
+            // ⚠️  not allowed as raw identifiers
+            "crate" | "self" | "super" => format!("{}_", ident),
+            _ => format!("r#{}", ident),
+        },
+        _ => ident,
+    }
+}
+
+/// "sample rx" -> SampleRx
+fn type_ident(name: &str) -> String {
+    let ident = words(name)
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        })
+        .collect::<String>();
+    match ident.chars().next() {
+        None => "Record".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("Record{}", ident),
+        _ if ident == "Self" => "Record".to_string(),
+        _ => ident,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create, Encoding, SampleSpec, TapeBuilder};
+
+    #[test]
+    fn sample_rx() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        let source = rust_struct("sample_rx", &schema);
+        assert!(source.contains("pub struct SampleRx {"));
+        assert!(source.contains(
+            "    #[serde(rename = \"NPI Number\")]\n    pub npi_number: i64,"
+        ));
+        assert!(source.contains("pub year_month: csv_simd::YearMonth,"));
+        assert_eq!(source, rust_struct("sample_rx", &schema));
+    }
+    #[test]
+    fn identifiers() {
+        let data =
+            b"id,Type,2015 total,,id,Paid?\n1,a,1.5,,2,yes\n2,b,,,3,no\n";
+        let tape = TapeBuilder::new().build_from_bytes(&data[..]).unwrap();
+        let source = rust_struct("", &tape.infer_schema(SampleSpec::All));
+        assert!(source.contains("pub struct Record {"));
+        assert!(source.contains("    pub id: i64,\n"));
+        assert!(source.contains("pub r#type: String,"));
+        assert!(source.contains("pub field_2015_total: Option<f64>,"));
+        assert!(source.contains("pub field_3: Option<String>,"));
+        assert!(source
+            .contains("    #[serde(rename = \"id\")]\n    pub id_2: i64,"));
+        assert!(source.contains("pub paid: bool,"));
+    }
+}
@@ -0,0 +1,398 @@
+///
+/// Column views
+///
+/// 🚧 Option 2 of "How read the tape?" (tape.rs): a counter that jumps through the index
+///    separate from the other counters.  The field of record r is hosted at
+///
+///    key = (r + 1) * jump + field_idx
+///
+///    So a column is read by striding the `StructureIndex` by `jump`; the other fields are
+///    never touched.
+///
+use std::borrow::Cow;
+use std::iter::FusedIterator;
+use std::ops::Range;
+
+use crate::dialect::Dialect;
+use crate::encoding::Encoding;
+use crate::error::StructureError;
+use crate::nulls::NullValues;
+use crate::number_format::NumberFormat;
+use crate::stage1::Slots;
+use crate::tape::{Header, Tape};
+use crate::temporal::{Date, DateFormat, DateTime, YearMonth};
+use crate::typed::{parse_field_with, FromField};
+
+/// How to select a column: by position or by name
+pub trait ColumnKey {
+    /// key -> position of the field in the record
+    fn field_idx(&self, header: &Header) -> Result<usize, StructureError>;
+}
+
+impl ColumnKey for usize {
+    fn field_idx(&self, header: &Header) -> Result<usize, StructureError> {
+        if *self >= header.field_cnt as usize {
+            return Err(StructureError::FieldOutOfRange {
+                field: *self,
+                field_cnt: header.field_cnt,
+            });
+        }
+        Ok(*self)
+    }
+}
+impl ColumnKey for u32 {
+    fn field_idx(&self, header: &Header) -> Result<usize, StructureError> {
+        (*self as usize).field_idx(header)
+    }
+}
+impl ColumnKey for &str {
+    fn field_idx(&self, header: &Header) -> Result<usize, StructureError> {
+        header.field_index(self)
+    }
+}
+impl ColumnKey for String {
+    fn field_idx(&self, header: &Header) -> Result<usize, StructureError> {
+        self.as_str().field_idx(header)
+    }
+}
+
+/// One field across a range of records
+#[derive(Debug, Clone, Copy)]
+pub struct Column<'tape> {
+    bytes: &'tape [u8],
+    index: Slots<'tape>,
+    jump: usize,
+    field_idx: usize,
+    encoding: Encoding,
+    dialect: Dialect,
+    number_format: NumberFormat,
+    nulls: &'tape NullValues,
+    /// records [start, end); excludes the header
+    start: u32,
+    end: u32,
+}
+
+impl<'tape> Column<'tape> {
+    pub(crate) fn new(tape: &'tape Tape, field_idx: usize) -> Self {
+        Column {
+            bytes: tape.bytes(),
+            index: tape.index().slots(),
+            jump: *tape.record_jump_size,
+            field_idx,
+            encoding: tape.header.encoding(),
+            dialect: tape.header.dialect(),
+            number_format: tape.header.number_format(),
+            nulls: tape.header.null_values(),
+            start: 0,
+            end: tape.record_cnt.saturating_sub(1),
+        }
+    }
+    /// The position of the field in the record
+    pub fn field_idx(&self) -> usize {
+        self.field_idx
+    }
+    /// The number of records in view
+    pub fn len(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The raw bytes of the field for the nth record in view
+    pub fn get(&self, nth: u32) -> Option<&'tape [u8]> {
+        if nth >= self.end - self.start {
+            return None;
+        }
+        let key = (self.start as usize + nth as usize + 1) * self.jump
+            + self.field_idx;
+        let start = self.index.get(key)? + 1;
+        let end = self.index.get(key + 1)?;
+        self.bytes.get(start..end)
+    }
+    /// The field transcoded from the source encoding to UTF-8
+    pub fn get_decoded(&self, nth: u32) -> Option<Cow<'tape, str>> {
+        self.get(nth).map(|bytes| self.encoding.decode(bytes))
+    }
+    /// The field without the enclosing quotes, with the escapes resolved, and transcoded to
+    /// UTF-8. Borrows unless an escape or transcoding is required.
+    /// ⚠️ A null token is returned as is; `get_value` is the null-aware variant.
+    pub fn value(&self, nth: u32) -> Option<Cow<'tape, str>> {
+        self.get(nth).map(|bytes| self.decode_value(bytes))
+    }
+    /// Iterate over the values of the field
+    pub fn values(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = Cow<'tape, str>> + ExactSizeIterator
+    {
+        let column = *self;
+        self.iter().map(move |bytes| column.decode_value(bytes))
+    }
+    /// The value of the field (see `value`); None when null or out of range.
+    pub fn get_value(&self, nth: u32) -> Option<Cow<'tape, str>> {
+        self.get(nth)
+            .filter(|raw| !self.nulls.is_null(raw, &self.dialect))
+            .map(|raw| self.decode_value(raw))
+    }
+    /// Is the field of the nth record null (see `NullValues`)
+    pub fn is_null(&self, nth: u32) -> bool {
+        self.get(nth)
+            .is_some_and(|raw| self.nulls.is_null(raw, &self.dialect))
+    }
+    /// Iterate over the values of the field; None for a null
+    pub fn nullable_values(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = Option<Cow<'tape, str>>> + ExactSizeIterator
+    {
+        let column = *self;
+        self.iter().map(move |raw| {
+            match column.nulls.is_null(raw, &column.dialect) {
+                true => None,
+                false => Some(column.decode_value(raw)),
+            }
+        })
+    }
+    /// The value of the field for the nth record parsed to a type; None when null.
+    pub fn get_typed<T: FromField>(
+        &self,
+        nth: u32,
+    ) -> Result<Option<T>, StructureError> {
+        let format = self.number_format;
+        self.get_with(nth, T::KIND, |value| T::from_formatted(value, &format))
+    }
+    /// The nth value with a custom parser
+    fn get_with<T>(
+        &self,
+        nth: u32,
+        kind: &'static str,
+        parse: impl FnOnce(&[u8]) -> Option<T>,
+    ) -> Result<Option<T>, StructureError> {
+        match self.get(nth) {
+            None => Ok(None),
+            Some(raw) => self.parse_with(nth, raw, kind, parse),
+        }
+    }
+    pub fn get_i64(&self, nth: u32) -> Result<Option<i64>, StructureError> {
+        self.get_typed(nth)
+    }
+    pub fn get_u64(&self, nth: u32) -> Result<Option<u64>, StructureError> {
+        self.get_typed(nth)
+    }
+    pub fn get_f64(&self, nth: u32) -> Result<Option<f64>, StructureError> {
+        self.get_typed(nth)
+    }
+    pub fn get_bool(&self, nth: u32) -> Result<Option<bool>, StructureError> {
+        self.get_typed(nth)
+    }
+    /// ISO (2016-09-30) or US (09/30/2016)
+    pub fn get_date(&self, nth: u32) -> Result<Option<Date>, StructureError> {
+        self.get_typed(nth)
+    }
+    /// 2016-09
+    pub fn get_year_month(
+        &self,
+        nth: u32,
+    ) -> Result<Option<YearMonth>, StructureError> {
+        self.get_typed(nth)
+    }
+    /// ISO timestamp
+    pub fn get_datetime(
+        &self,
+        nth: u32,
+    ) -> Result<Option<DateTime>, StructureError> {
+        self.get_typed(nth)
+    }
+    pub fn get_date_with(
+        &self,
+        nth: u32,
+        format: &DateFormat,
+    ) -> Result<Option<Date>, StructureError> {
+        self.get_with(nth, Date::KIND, |value| format.parse_date(value))
+    }
+    pub fn get_datetime_with(
+        &self,
+        nth: u32,
+        format: &DateFormat,
+    ) -> Result<Option<DateTime>, StructureError> {
+        self.get_with(nth, DateTime::KIND, |value| format.parse_datetime(value))
+    }
+    /// Parse the column into the buffer (cleared first; the allocation is reused).
+    /// Stops on the first value that does not parse.
+    pub fn parse_into<T: FromField>(
+        &self,
+        acc: &mut Vec<Option<T>>,
+    ) -> Result<(), StructureError> {
+        acc.clear();
+        acc.reserve(self.len());
+        for (nth, raw) in self.iter().enumerate() {
+            acc.push(self.parse(nth as u32, raw)?);
+        }
+        Ok(())
+    }
+    pub fn parse_i64_into(
+        &self,
+        acc: &mut Vec<Option<i64>>,
+    ) -> Result<(), StructureError> {
+        self.parse_into(acc)
+    }
+    /// raw -> typed value; the error hosts the position in the Tape
+    fn parse<T: FromField>(
+        &self,
+        nth: u32,
+        raw: &[u8],
+    ) -> Result<Option<T>, StructureError> {
+        let format = self.number_format;
+        self.parse_with(nth, raw, T::KIND, |value| {
+            T::from_formatted(value, &format)
+        })
+    }
+    fn parse_with<T>(
+        &self,
+        nth: u32,
+        raw: &[u8],
+        kind: &'static str,
+        parse: impl FnOnce(&[u8]) -> Option<T>,
+    ) -> Result<Option<T>, StructureError> {
+        if self.nulls.is_null(raw, &self.dialect) {
+            return Ok(None);
+        }
+        parse_field_with(
+            raw,
+            (&self.dialect, self.encoding),
+            (self.start + nth, self.field_idx as u32),
+            kind,
+            parse,
+        )
+    }
+    /// raw -> value
+    fn decode_value(&self, raw: &'tape [u8]) -> Cow<'tape, str> {
+        self.encoding.decode_cow(self.dialect.unescape(raw))
+    }
+    /// A view of a range of the records in this view; None when out of range.
+    pub fn slice(&self, records: Range<u32>) -> Option<Column<'tape>> {
+        if records.start > records.end || records.end > self.end - self.start {
+            return None;
+        }
+        Some(Column {
+            start: self.start + records.start,
+            end: self.start + records.end,
+            ..*self
+        })
+    }
+    /// The column with another number format (e.g. one column of a mixed export)
+    pub fn with_number_format(&self, number_format: NumberFormat) -> Self {
+        Column {
+            number_format,
+            ..*self
+        }
+    }
+    /// The column with other null tokens
+    pub fn with_null_values(&self, nulls: &'tape NullValues) -> Self {
+        Column { nulls, ..*self }
+    }
+    /// The position of the first record of the view in the Tape
+    pub fn first_record(&self) -> u32 {
+        self.start
+    }
+    /// Iterate over the raw bytes of the field
+    pub fn iter(&self) -> ColumnIter<'tape> {
+        ColumnIter {
+            column: *self,
+            front: 0,
+            back: self.end - self.start,
+        }
+    }
+}
+
+impl<'tape> IntoIterator for Column<'tape> {
+    type Item = &'tape [u8];
+    type IntoIter = ColumnIter<'tape>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over one field across the records of a Column
+#[derive(Debug, Clone)]
+pub struct ColumnIter<'tape> {
+    column: Column<'tape>,
+    front: u32,
+    back: u32,
+}
+
+impl<'tape> Iterator for ColumnIter<'tape> {
+    type Item = &'tape [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.front += 1;
+        self.column.get(self.front - 1)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front) as usize;
+        (len, Some(len))
+    }
+    /// random-access; jump directly to the record
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.len() {
+            self.front = self.back;
+            return None;
+        }
+        self.front += n as u32;
+        self.next()
+    }
+}
+impl<'tape> DoubleEndedIterator for ColumnIter<'tape> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.column.get(self.back)
+    }
+}
+impl<'tape> ExactSizeIterator for ColumnIter<'tape> {}
+impl<'tape> FusedIterator for ColumnIter<'tape> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{create, Encoding, StructureError};
+
+    #[test]
+    fn column() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let by_name = tape.column("Practitioner State").unwrap();
+        let by_idx = tape.column(3_usize).unwrap();
+        assert_eq!(by_name.field_idx(), 3);
+        assert_eq!(by_name.len(), 7);
+        assert!(by_name.iter().eq(by_idx.iter()));
+        assert_eq!(by_name.iter().next_back(), Some(&b"F"[..]));
+
+        let counts = tape.column("NRx Count").unwrap();
+        let counts = counts.iter().collect::<Vec<_>>();
+        assert_eq!(counts, vec![&b"2"[..], b"1", b"2", b"2", b"2", b"1", b"1"]);
+
+        let specialty = tape.column(2_usize).unwrap();
+        assert_eq!(specialty.iter().nth(4), Some(&b"FAMILY "[..]));
+        let slice = specialty.slice(1..3).unwrap();
+        assert_eq!(slice.len(), 2);
+        assert_eq!(slice.first_record(), 1);
+        assert_eq!(slice.get(1), Some(&b"FAMILY PRACTICE"[..]));
+        assert_eq!(slice.iter().rev().count(), 2);
+        assert!(specialty.slice(5..8).is_none());
+        let values = specialty.values().collect::<Vec<_>>();
+        assert_eq!(values[1], "INTERNAL MED, CARD. ELECTROPHYSIOLOGY");
+        assert!(matches!(values[0], std::borrow::Cow::Borrowed(_)));
+
+        assert!(matches!(
+            tape.column("NRx"),
+            Err(StructureError::UnknownField { .. })
+        ));
+        assert!(matches!(
+            tape.column(8_usize),
+            Err(StructureError::FieldOutOfRange { field: 8, .. })
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+///
+/// Frequency tables
+///
+/// value -> number of records, for a column or a combination of columns.  The keys are the
+/// values of the fields borrowed from the data (see `Record::value`); a value is copied only
+/// when it has escapes or is transcoded.  The chunks of the records (see `Tape::chunks`) are
+/// counted in parallel, then the tables are merged.
+///
+/// 🔑 A null value (see `NullValues`) is not a key; the record is counted in `null_cnt`.
+///
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::error::StructureError;
+use crate::records::Record;
+use crate::tape::Tape;
+
+/// The number of records of each value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueCounts<K: Hash + Eq> {
+    counts: HashMap<K, u64>,
+    null_cnt: u64,
+}
+
+impl<K: Hash + Eq> Default for ValueCounts<K> {
+    fn default() -> Self {
+        ValueCounts {
+            counts: HashMap::new(),
+            null_cnt: 0,
+        }
+    }
+}
+
+impl<K: Hash + Eq + Ord> ValueCounts<K> {
+    /// The number of distinct values
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+    /// The number of records with a null value
+    pub fn null_cnt(&self) -> u64 {
+        self.null_cnt
+    }
+    /// The number of records counted, null included
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum::<u64>() + self.null_cnt
+    }
+    /// The count of a value; 0 when not found
+    pub fn get<Q>(&self, value: &Q) -> u64
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.counts.get(value).copied().unwrap_or(0)
+    }
+    /// In no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, u64)> {
+        self.counts.iter().map(|(value, &cnt)| (value, cnt))
+    }
+    /// Most frequent first; a tie in the order of the values
+    pub fn sorted(&self) -> Vec<(&K, u64)> {
+        let mut sorted = self.iter().collect::<Vec<_>>();
+        sorted.sort_unstable_by(most_frequent);
+        sorted
+    }
+    /// The `n` most frequent, in the order of `sorted`
+    pub fn top(&self, n: usize) -> Vec<(&K, u64)> {
+        let mut top = self.iter().collect::<Vec<_>>();
+        if n < top.len() {
+            top.select_nth_unstable_by(n, most_frequent);
+            top.truncate(n);
+        }
+        top.sort_unstable_by(most_frequent);
+        top
+    }
+    /// In the order of the values
+    pub fn sorted_by_value(&self) -> Vec<(&K, u64)> {
+        let mut sorted = self.iter().collect::<Vec<_>>();
+        sorted.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        sorted
+    }
+    fn merge(&mut self, other: ValueCounts<K>) {
+        self.null_cnt += other.null_cnt;
+        for (value, cnt) in other.counts {
+            *self.counts.entry(value).or_insert(0) += cnt;
+        }
+    }
+}
+
+fn most_frequent<K: Ord>(a: &(&K, u64), b: &(&K, u64)) -> std::cmp::Ordering {
+    b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0))
+}
+
+/// Count the keys of the records, in parallel over `chunks`; None is null.
+pub(crate) fn count<'tape, K, F>(
+    tape: &'tape Tape,
+    chunks: u8,
+    key: F,
+) -> Result<ValueCounts<K>, StructureError>
+where
+    K: Hash + Eq + Ord + Send,
+    F: Fn(&Record<'tape>) -> Option<K> + Sync,
+{
+    let ranges = tape.record_ranges(chunks)?;
+    let parts = std::thread::scope(|scope| {
+        let key = &key;
+        ranges
+            .into_iter()
+            .map(|(first, record_cnt)| {
+                scope.spawn(move || {
+                    let mut counts = ValueCounts::default();
+                    let records =
+                        tape.as_records().skip(first).take(record_cnt);
+                    for record in records {
+                        match key(&record) {
+                            Some(value) => {
+                                *counts.counts.entry(value).or_insert(0) += 1
+                            }
+                            None => counts.null_cnt += 1,
+                        }
+                    }
+                    counts
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().expect("a counting thread"))
+            .collect::<Vec<_>>()
+    });
+    let mut counts = ValueCounts::default();
+    for part in parts {
+        counts.merge(part);
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create, Encoding, NullValues, TapeBuilder};
+    use std::borrow::Cow;
+
+    #[test]
+    fn sample_rx() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let payments = tape.value_counts("Payment Type Group").unwrap();
+        assert_eq!(payments.len(), 3);
+        assert_eq!(payments.total(), 7);
+        assert_eq!(payments.get("CASH"), 4);
+        assert_eq!(payments.get("CASH,IT"), 1);
+        assert_eq!(
+            payments.sorted(),
+            vec![
+                (&"CASH".into(), 4),
+                (&"TOTAL THIRD PARTY".into(), 2),
+                (&"CASH,IT".into(), 1)
+            ]
+        );
+        let specialties = tape.value_counts("Primary Specialty Desc").unwrap();
+        let top = specialties.top(1);
+        assert_eq!(top, vec![(&"FAMILY PRACTICE".into(), 3)]);
+        // chunked or not, the same table
+        let field_idx = tape.field_index("Primary Specialty Desc").unwrap();
+        for chunks in 1..=4 {
+            let counts =
+                count(&tape, chunks, |record| record.get_value(field_idx))
+                    .unwrap();
+            assert_eq!(counts, specialties);
+        }
+
+        let pairs = tape
+            .value_counts_multi(&["Payment Type Group", "Year-Month"])
+            .unwrap();
+        let key = [Cow::from("CASH"), Cow::from("2016-08")];
+        assert_eq!(pairs.get(&key[..]), 2);
+        assert_eq!(pairs.sorted_by_value()[0].0[1], "2015-03");
+        assert_eq!(pairs.total(), 7);
+    }
+    #[test]
+    fn nulls_and_escapes() {
+        let data = b"name\n\"a\"\"b\"\nNA\n\"\"\n\"a\"\"b\"\nc\n";
+        let tape = TapeBuilder::new()
+            .null_values(NullValues::common())
+            .build_from_bytes(&data[..])
+            .unwrap();
+        let counts = tape.value_counts(0_usize).unwrap();
+        assert_eq!(counts.null_cnt(), 2);
+        assert_eq!(counts.sorted()[0], (&"a\"b".into(), 2));
+        assert!(tape.value_counts("missing").is_err());
+    }
+}
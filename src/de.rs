@@ -0,0 +1,492 @@
+///
+/// Serde deserialization of the records
+///
+/// A record is a map of the field names to the values (a struct), or a sequence of the values
+/// (a tuple or a tuple struct).  A value is a str (borrowed from the data when there is no
+/// escape), a number or a boolean (see `FromField` and `NumberFormat`), or an Option (see
+/// `NullValues`).
+///
+/// 🔑 `tape.deserialize::<T>()` borrows the Tape; a `&'tape str` field borrows the data.
+///
+use std::marker::PhantomData;
+
+use serde::de::{
+    self, value::BorrowedStrDeserializer, DeserializeSeed, IntoDeserializer,
+    Visitor,
+};
+use serde::Deserialize;
+
+use crate::error::StructureError;
+use crate::records::{Record, Records};
+use crate::typed::FromField;
+
+/// Iterator over the records of a Tape deserialized to `T`
+pub struct DeserializeRecords<'tape, T> {
+    records: Records<'tape>,
+    names: &'tape [String],
+    ty: PhantomData<T>,
+}
+
+impl<'tape, T> DeserializeRecords<'tape, T> {
+    pub(crate) fn new(records: Records<'tape>, names: &'tape [String]) -> Self {
+        DeserializeRecords {
+            records,
+            names,
+            ty: PhantomData,
+        }
+    }
+}
+
+impl<'tape, T: Deserialize<'tape>> Iterator for DeserializeRecords<'tape, T> {
+    type Item = Result<T, StructureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.records.next()?;
+        Some(deserialize_record(record, self.names))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.records.size_hint()
+    }
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let record = self.records.nth(n)?;
+        Some(deserialize_record(record, self.names))
+    }
+}
+
+impl<'tape, T: Deserialize<'tape>> ExactSizeIterator
+    for DeserializeRecords<'tape, T>
+{
+}
+
+/// A record -> `T`; the names select the fields of a struct
+pub fn deserialize_record<'tape, T: Deserialize<'tape>>(
+    record: Record<'tape>,
+    names: &'tape [String],
+) -> Result<T, StructureError> {
+    T::deserialize(RecordDeserializer { record, names })
+        .map_err(|error| locate(error, record.idx()))
+}
+
+/// Host the record in a message from serde
+fn locate(error: StructureError, record: u32) -> StructureError {
+    match error {
+        StructureError::Deserialize {
+            record: None,
+            field,
+            message,
+        } => StructureError::Deserialize {
+            record: Some(record),
+            field,
+            message,
+        },
+        error => error,
+    }
+}
+
+/// A record: a map of the names to the values, or a sequence of the values
+struct RecordDeserializer<'tape> {
+    record: Record<'tape>,
+    names: &'tape [String],
+}
+
+impl<'de> de::Deserializer<'de> for RecordDeserializer<'de> {
+    type Error = StructureError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+    fn deserialize_map<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(Fields {
+            record: self.record,
+            names: Some(self.names),
+            field: 0,
+        })
+    }
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(Fields {
+            record: self.record,
+            names: None,
+            field: 0,
+        })
+    }
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+/// The fields of a record; by name (map) or by position (seq)
+struct Fields<'tape> {
+    record: Record<'tape>,
+    names: Option<&'tape [String]>,
+    field: usize,
+}
+
+impl<'tape> Fields<'tape> {
+    /// The value of the current field; the error hosts the field
+    fn value<T: DeserializeSeed<'tape>>(
+        &mut self,
+        seed: T,
+    ) -> Result<T::Value, StructureError> {
+        let field = self.field;
+        self.field += 1;
+        seed.deserialize(FieldDeserializer {
+            record: self.record,
+            field,
+        })
+        .map_err(|error| match error {
+            StructureError::Deserialize {
+                record: None,
+                field: None,
+                message,
+            } => StructureError::Deserialize {
+                record: None,
+                field: Some(field as u32),
+                message,
+            },
+            error => error,
+        })
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Fields<'de> {
+    type Error = StructureError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let names = self.names.unwrap_or_default();
+        match names.get(self.field) {
+            Some(name) if self.field < self.record.len() => seed
+                .deserialize(BorrowedStrDeserializer::new(name.as_str()))
+                .map(Some),
+            _ => Ok(None),
+        }
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.value(seed)
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.record.len() - self.field)
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Fields<'de> {
+    type Error = StructureError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        match self.field < self.record.len() {
+            true => self.value(seed).map(Some),
+            false => Ok(None),
+        }
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.record.len() - self.field)
+    }
+}
+
+/// The value of a field
+struct FieldDeserializer<'tape> {
+    record: Record<'tape>,
+    field: usize,
+}
+
+impl<'tape> FieldDeserializer<'tape> {
+    /// A typed value; a null is an error (use an Option)
+    fn typed<T: FromField>(&self) -> Result<T, StructureError> {
+        self.record.get_typed(self.field)?.ok_or_else(|| {
+            de::Error::custom(format!("missing {} value", T::KIND))
+        })
+    }
+}
+
+macro_rules! deserialize_typed {
+    ($($method:ident => $visit:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.typed::<$ty>()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for FieldDeserializer<'de> {
+    type Error = StructureError;
+
+    /// The most specific of: integer, float, boolean, str
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if self.record.is_null(self.field) {
+            return visitor.visit_none();
+        }
+        if let Ok(Some(value)) = self.record.get_typed::<i64>(self.field) {
+            return visitor.visit_i64(value);
+        }
+        if let Ok(Some(value)) = self.record.get_typed::<u64>(self.field) {
+            return visitor.visit_u64(value);
+        }
+        if let Ok(Some(value)) = self.record.get_typed::<f64>(self.field) {
+            return visitor.visit_f64(value);
+        }
+        if let Ok(Some(value)) = self.record.get_typed::<bool>(self.field) {
+            return visitor.visit_bool(value);
+        }
+        self.deserialize_str(visitor)
+    }
+
+    deserialize_typed! {
+        deserialize_bool => visit_bool(bool),
+        deserialize_i8 => visit_i64(i64),
+        deserialize_i16 => visit_i64(i64),
+        deserialize_i32 => visit_i64(i64),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_u8 => visit_u64(u64),
+        deserialize_u16 => visit_u64(u64),
+        deserialize_u32 => visit_u64(u64),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_f32 => visit_f64(f64),
+        deserialize_f64 => visit_f64(f64),
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+    /// Borrowed unless an escape or transcoding is required
+    fn deserialize_str<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.record.value(self.field) {
+            Some(std::borrow::Cow::Borrowed(value)) => {
+                visitor.visit_borrowed_str(value)
+            }
+            Some(std::borrow::Cow::Owned(value)) => visitor.visit_string(value),
+            None => Err(de::Error::custom("missing field")),
+        }
+    }
+    fn deserialize_string<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+    /// The raw bytes of the field
+    fn deserialize_bytes<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.record.get(self.field) {
+            Some(raw) => visitor.visit_borrowed_bytes(raw),
+            None => Err(de::Error::custom("missing field")),
+        }
+    }
+    fn deserialize_byte_buf<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+    /// None when null (see `NullValues`)
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.record.is_null(self.field) {
+            true => visitor.visit_none(),
+            false => visitor.visit_some(self),
+        }
+    }
+    fn deserialize_unit<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    /// A unit variant named by the value
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.record.value(self.field) {
+            Some(value) => {
+                visitor.visit_enum(value.into_owned().into_deserializer())
+            }
+            None => Err(de::Error::custom("missing field")),
+        }
+    }
+    fn deserialize_identifier<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 seq tuple tuple_struct map struct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::{create, Encoding, NullValues, StructureError, TapeBuilder};
+
+    #[derive(Debug, Deserialize)]
+    struct Rx<'a> {
+        #[serde(rename = "NPI Number")]
+        npi: u64,
+        #[serde(rename = "Primary Specialty Desc")]
+        specialty: &'a str,
+        #[serde(rename = "Payment Type Group")]
+        payment: String,
+        #[serde(rename = "NRx Count")]
+        count: u8,
+    }
+
+    #[test]
+    fn by_name() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let rxs = tape
+            .deserialize::<Rx>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rxs.len(), 7);
+        assert_eq!(rxs[1].specialty, "INTERNAL MED, CARD. ELECTROPHYSIOLOGY");
+        assert_eq!(rxs[6].npi, 1003002819);
+        assert_eq!(rxs[6].payment, "CASH,IT");
+        assert_eq!(rxs.iter().map(|rx| rx.count as u32).sum::<u32>(), 11);
+        // borrowed from the data
+        let bytes = tape.bytes().as_ptr_range();
+        assert!(bytes.contains(&rxs[1].specialty.as_ptr()));
+    }
+    #[test]
+    fn by_position() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Kind {
+            Cash,
+            Card,
+        }
+        let data =
+            b"id,kind,amount,note\n1,Cash,NA,\n2,Card,2.5,\"say \"\"hi\"\"\"\n";
+        let tape = TapeBuilder::new()
+            .null_values(NullValues::common())
+            .build_from_bytes(&data[..])
+            .unwrap();
+        let rows = tape
+            .deserialize::<(u32, Kind, Option<f64>, Option<String>)>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows[0], (1, Kind::Cash, None, None));
+        assert_eq!(
+            rows[1],
+            (2, Kind::Card, Some(2.5), Some("say \"hi\"".into()))
+        );
+
+        #[derive(Debug, Deserialize)]
+        struct Strict {
+            #[allow(dead_code)]
+            amount: f64,
+        }
+        let error = tape.deserialize::<Strict>().next().unwrap().unwrap_err();
+        assert!(matches!(
+            error,
+            StructureError::Deserialize {
+                record: Some(0),
+                field: Some(2),
+                ..
+            }
+        ));
+        #[derive(Debug, Deserialize)]
+        struct Borrowed<'a> {
+            #[allow(dead_code)]
+            note: &'a str,
+        }
+        let error = tape.deserialize::<Borrowed>().nth(1).unwrap().unwrap_err();
+        assert!(matches!(
+            error,
+            StructureError::Deserialize {
+                record: Some(1),
+                field: Some(3),
+                ..
+            }
+        ));
+    }
+}
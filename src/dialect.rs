@@ -0,0 +1,162 @@
+///
+/// The csv dialect: how the fields are delimited, quoted and escaped
+///
+/// A field is "raw" when read directly from the data (quotes and escapes included).  The value
+/// of the field is the raw field without the enclosing quotes, and with the escapes resolved.
+///
+/// 🔑 Most fields are not quoted, or quoted without an escape; those values borrow from the data.
+///    Only a field with an escape requires an allocation.
+///
+use std::borrow::Cow;
+
+/// How a quote is escaped inside of a quoted field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Escape {
+    /// RFC 4180: `""` -> `"`
+    #[default]
+    Doubled,
+    /// The byte escapes the byte that follows: `\"` -> `"`, `\\` -> `\`
+    Backslash(u8),
+}
+
+/// Which values have the leading and trailing (ASCII) whitespace removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Trim {
+    None,
+    /// The field names only
+    #[default]
+    Headers,
+    /// The values of the fields only
+    Fields,
+    All,
+}
+
+impl Trim {
+    pub fn headers(&self) -> bool {
+        matches!(self, Trim::Headers | Trim::All)
+    }
+    pub fn fields(&self) -> bool {
+        matches!(self, Trim::Fields | Trim::All)
+    }
+}
+
+/// Structure and quoting of the csv data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub escape: Escape,
+    pub trim: Trim,
+    /// `""` is an empty string; only an unquoted empty field is null (see `NullValues`)
+    pub quoted_empty_is_value: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect {
+            delimiter: b',',
+            quote: b'"',
+            escape: Escape::Doubled,
+            trim: Trim::default(),
+            quoted_empty_is_value: false,
+        }
+    }
+}
+
+impl Dialect {
+    /// Is the raw field enclosed in quotes
+    pub fn is_quoted(&self, raw: &[u8]) -> bool {
+        raw.len() >= 2
+            && raw[0] == self.quote
+            && raw[raw.len() - 1] == self.quote
+    }
+    /// raw field -> value; trim (per `Trim`), strip the enclosing quotes and resolve the
+    /// escapes.
+    pub fn unescape<'a>(&self, raw: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.trim.fields() {
+            self.unquote(raw.trim_ascii())
+        } else {
+            self.unquote(raw)
+        }
+    }
+    /// Strip the enclosing quotes and resolve the escapes
+    pub fn unquote<'a>(&self, raw: &'a [u8]) -> Cow<'a, [u8]> {
+        let inner = if self.is_quoted(raw) {
+            &raw[1..raw.len() - 1]
+        } else {
+            raw
+        };
+        match self.escape {
+            Escape::Doubled => {
+                if !self.is_quoted(raw) || !inner.contains(&self.quote) {
+                    return Cow::Borrowed(inner);
+                }
+                let mut value = Vec::with_capacity(inner.len());
+                let mut bytes = inner.iter().peekable();
+                while let Some(&byte) = bytes.next() {
+                    value.push(byte);
+                    if byte == self.quote && bytes.peek() == Some(&&self.quote)
+                    {
+                        bytes.next();
+                    }
+                }
+                Cow::Owned(value)
+            }
+            Escape::Backslash(escape) => {
+                if !inner.contains(&escape) {
+                    return Cow::Borrowed(inner);
+                }
+                let mut value = Vec::with_capacity(inner.len());
+                let mut bytes = inner.iter();
+                while let Some(&byte) = bytes.next() {
+                    match byte {
+                        b if b == escape => match bytes.next() {
+                            Some(&escaped) => value.push(escaped),
+                            None => value.push(byte),
+                        },
+                        _ => value.push(byte),
+                    }
+                }
+                Cow::Owned(value)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubled() {
+        let dialect = Dialect::default();
+        let value = dialect.unescape(b"\"INTERNAL MED, CARD.\"");
+        assert!(matches!(value, Cow::Borrowed(b"INTERNAL MED, CARD.")));
+        let value = dialect.unescape(b"\"say \"\"hi\"\"\"");
+        assert_eq!(&value[..], b"say \"hi\"");
+        assert_eq!(&dialect.unescape(b"\"\"")[..], b"");
+        // unquoted fields are left as-is
+        assert_eq!(&dialect.unescape(b"a\"\"b")[..], b"a\"\"b");
+    }
+    #[test]
+    fn backslash() {
+        let dialect = Dialect {
+            escape: Escape::Backslash(b'\\'),
+            ..Dialect::default()
+        };
+        assert_eq!(&dialect.unescape(b"\"say \\\"hi\\\"\"")[..], b"say \"hi\"");
+        assert_eq!(&dialect.unescape(b"a\\\\b")[..], b"a\\b");
+        assert!(matches!(
+            dialect.unescape(b"plain"),
+            Cow::Borrowed(b"plain")
+        ));
+    }
+    #[test]
+    fn trim() {
+        let mut dialect = Dialect::default();
+        assert_eq!(&dialect.unescape(b" \"a\" ")[..], b" \"a\" ");
+        dialect.trim = Trim::Fields;
+        assert_eq!(&dialect.unescape(b" \" a\" ")[..], b" a");
+    }
+}
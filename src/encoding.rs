@@ -0,0 +1,112 @@
+///
+/// Input encodings
+///
+/// The structure of a csv file (comma, quote, CR, LF) is ASCII in every encoding supported here.
+/// So the Stage1 index is the same regardless of the encoding.  What changes is how the bytes
+/// between the structural code-units are presented to the caller.
+///
+/// 🔑 Decoding is lazy: a field is only transcoded when it is accessed, and only when it holds
+///    a non-ASCII byte.  A mostly-ASCII file does not pay for a copy.
+///
+use std::borrow::Cow;
+
+/// A tag that sometimes prefixes data sources to be ignored by the csv app.
+pub(crate) const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// The encoding of the bytes hosted by the Tape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// The default; multi-byte code-points
+    #[default]
+    Utf8,
+    /// Single-byte; the 0x80-0x9F range hosts the "smart quotes", the euro sign etc.
+    Windows1252,
+    /// Single-byte; ISO-8859-1, every byte maps to the code-point with the same value
+    Latin1,
+}
+
+/// Windows-1252 code-points for the 0x80-0x9F range. The five bytes not defined by the
+/// code page map to the C1 control with the same value (the WHATWG convention).
+const WINDOWS_1252_C1: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}',
+    '\u{2020}', '\u{2021}', '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}',
+    '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}', '\u{0090}', '\u{2018}',
+    '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}',
+    '\u{017E}', '\u{0178}',
+];
+
+impl Encoding {
+    /// Single-byte encodings: one code-unit per code-point.
+    pub fn is_single_byte(&self) -> bool {
+        match *self {
+            Encoding::Utf8 => false,
+            Encoding::Windows1252 | Encoding::Latin1 => true,
+        }
+    }
+    /// bytes -> UTF-8
+    ///
+    /// Returns `Cow::Borrowed` when the bytes are already valid UTF-8 in the target (all ASCII
+    /// for the single-byte encodings). Invalid UTF-8 in a `Utf8` source is replaced with U+FFFD.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Cow<'a, str> {
+        match *self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes),
+            _ if bytes.is_ascii() => {
+                // 👍 Safety: ASCII is valid UTF-8
+                Cow::Borrowed(unsafe { std::str::from_utf8_unchecked(bytes) })
+            }
+            Encoding::Windows1252 => Cow::Owned(
+                bytes.iter().map(|&byte| windows_1252_char(byte)).collect(),
+            ),
+            Encoding::Latin1 => {
+                Cow::Owned(bytes.iter().map(|&byte| byte as char).collect())
+            }
+        }
+    }
+    /// `decode` for bytes that may already be owned; e.g., an unescaped field
+    pub fn decode_cow<'a>(&self, bytes: Cow<'a, [u8]>) -> Cow<'a, str> {
+        match bytes {
+            Cow::Borrowed(bytes) => self.decode(bytes),
+            Cow::Owned(bytes) => match *self {
+                Encoding::Utf8 => match String::from_utf8(bytes) {
+                    Ok(value) => Cow::Owned(value),
+                    Err(e) => Cow::Owned(
+                        String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                    ),
+                },
+                _ => Cow::Owned(self.decode(&bytes).into_owned()),
+            },
+        }
+    }
+}
+
+/// byte -> char for Windows-1252
+#[inline]
+fn windows_1252_char(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => WINDOWS_1252_C1[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_is_borrowed() {
+        let decoded = Encoding::Windows1252.decode(b"FAMILY PRACTICE");
+        assert!(matches!(decoded, Cow::Borrowed("FAMILY PRACTICE")));
+    }
+    #[test]
+    fn windows_1252() {
+        let decoded = Encoding::Windows1252.decode(b"Caf\xe9 \x80 \x93x\x94");
+        assert_eq!(decoded, "Caf\u{e9} \u{20ac} \u{201c}x\u{201d}");
+    }
+    #[test]
+    fn latin1() {
+        let decoded = Encoding::Latin1.decode(b"Caf\xe9 \x80");
+        assert_eq!(decoded, "Caf\u{e9} \u{80}");
+    }
+}
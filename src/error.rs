@@ -1,6 +1,7 @@
 use std::fmt;
 
 use std::io;
+use std::path::PathBuf;
 use thiserror::Error;
 
 /// WIP
@@ -18,6 +19,78 @@ pub enum StructureError {
     InvalidState,
     #[error("Unsupported csv structure: likely variable number of fields")]
     InvalidCsvFormat,
+    /// The index does not host the key; a corrupt or mismatched index
+    #[error("Index key {key} is out of bounds (index len: {len})")]
+    IndexOutOfBounds { key: usize, len: usize },
+    /// The index points outside of the data
+    #[error("Invalid span {start}..{end} (data len: {len})")]
+    InvalidSpan {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    /// The bytes are not valid UTF-8
+    #[error("Invalid UTF-8 in record {record} field {field:?} after {valid_up_to} bytes")]
+    InvalidUtf8 {
+        record: u32,
+        field: Option<u32>,
+        valid_up_to: usize,
+    },
+    /// The header does not host the field name
+    #[error("Unknown field: {name:?} (near: {near:?})")]
+    UnknownField { name: String, near: Vec<String> },
+    /// A builder option that cannot be applied
+    #[error("Invalid option {option}: {reason}")]
+    InvalidOption {
+        option: &'static str,
+        reason: String,
+    },
+    /// The record does not have the number of fields of the header
+    #[error(
+        "Record {record} does not have the number of fields of the header"
+    )]
+    RaggedRecord { record: u32 },
+    /// The field position exceeds the number of fields
+    #[error("Field {field} is out of range (field count: {field_cnt})")]
+    FieldOutOfRange { field: usize, field_cnt: u32 },
+    /// The value of the field does not parse to the type
+    #[error("Invalid {kind} in record {record} field {field}: {value:?}")]
+    InvalidValue {
+        record: u32,
+        field: u32,
+        kind: &'static str,
+        value: String,
+    },
+    /// A record does not deserialize to the type (see `Tape::deserialize`)
+    #[error("Cannot deserialize record {record:?} field {field:?}: {message}")]
+    Deserialize {
+        record: Option<u32>,
+        field: Option<u32>,
+        message: String,
+    },
+    /// No header and no records (after the BOM and the preamble)
+    #[error("The data is empty")]
+    EmptyInput,
+    /// A declared Schema that cannot be read (see `Schema::from_text`)
+    #[error("Invalid schema at line {line}: {reason}")]
+    InvalidSchema { line: usize, reason: String },
+    /// The file changed after the Tape was created
+    #[error("The file changed after the Tape was created: {path:?}")]
+    StaleSource { path: PathBuf },
+    /// The index differs from the one computed by the reference reader
+    #[error(
+        "Index mismatch at key {key}: expected {expected:?} found {found:?} \
+         near {context:?}"
+    )]
+    IndexMismatch {
+        key: usize,
+        expected: Option<usize>,
+        found: Option<usize>,
+        context: String,
+    },
+    /// Another process holds an exclusive lock on the file
+    #[error("The file is locked by another process: {path:?}")]
+    SourceLocked { path: PathBuf },
 }
 //------------------------------------------------------------------------------
 // Error implementation
@@ -33,6 +106,15 @@ impl From<io::Error> for StructureError {
         StructureError::Io { source: err }
     }
 }
+impl serde::de::Error for StructureError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        StructureError::Deserialize {
+            record: None,
+            field: None,
+            message: msg.to_string(),
+        }
+    }
+}
 impl<T> From<std::result::Result<T, StructureError>> for StructureError {
     fn from(err: std::result::Result<T, StructureError>) -> StructureError {
         StructureError::InvalidState
@@ -0,0 +1,84 @@
+///
+/// Protection of a Tape from changes to the file that hosts the data
+///
+/// A memory map shares the pages of the file. When another process truncates or rewrites the
+/// file, a read through the map can SIGBUS, or silently return the new content.  The index no
+/// longer describes the data.
+///
+/// The options, from least to most costly:
+/// * `SourceStamp`: always recorded for a file; a cheap size + mtime check (`Tape::is_stale`)
+/// * `Protection::Lock`: advisory shared lock held for the life of the Tape
+/// * `Protection::Copy`: read the file into memory; the Tape no longer depends on the file
+///
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::error::StructureError;
+
+/// How to host the bytes of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Protection {
+    /// Memory map the file; rely on `Tape::is_stale` to detect changes
+    #[default]
+    None,
+    /// Memory map the file whilst holding an advisory shared lock
+    Lock,
+    /// Read the file into memory
+    Copy,
+}
+
+/// The size and modification time of the file when the Tape was created.
+#[derive(Debug, Clone)]
+pub struct SourceStamp {
+    pub path: PathBuf,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl SourceStamp {
+    /// Record the current state of an open file
+    pub fn capture(path: &Path, file: &File) -> Result<Self, StructureError> {
+        let meta = file.metadata()?;
+        Ok(SourceStamp {
+            path: path.to_path_buf(),
+            len: meta.len(),
+            modified: meta.modified().ok(),
+        })
+    }
+    /// A file that can no longer be read is stale.
+    pub fn is_stale(&self) -> bool {
+        match std::fs::metadata(&self.path) {
+            Err(_) => true,
+            Ok(meta) => {
+                meta.len() != self.len || meta.modified().ok() != self.modified
+            }
+        }
+    }
+}
+
+/// An advisory shared lock; released when the file is closed (dropped).
+#[derive(Debug)]
+pub struct FileLock(File);
+
+impl FileLock {
+    /// Fails when another process holds an exclusive lock.
+    pub fn acquire(path: &Path, file: File) -> Result<Self, StructureError> {
+        match file.try_lock_shared() {
+            Ok(()) => Ok(FileLock(file)),
+            Err(TryLockError::WouldBlock) => {
+                Err(StructureError::SourceLocked {
+                    path: path.to_path_buf(),
+                })
+            }
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // closing the file releases the lock; unlock explicitly regardless
+        let _ = self.0.unlock();
+    }
+}
@@ -0,0 +1,160 @@
+///
+/// Field name -> field position
+///
+/// The map is computed once per Header; a lookup is a hash of the (normalized) name.
+///
+/// 🔑 A vendor that reorders the columns does not break a lookup by name.  A vendor that renames
+///    a column does; the error lists the names that are "near" the missing name.
+///
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::error::StructureError;
+
+/// How a name is matched with the field names of the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NameMatching {
+    /// "NRx Count" matches "nrx count"
+    pub case_insensitive: bool,
+    /// " NRx   Count" matches "NRx Count"; the runs of whitespace collapse to one space
+    pub normalize_whitespace: bool,
+}
+
+impl NameMatching {
+    /// Exact match
+    pub fn exact() -> Self {
+        NameMatching::default()
+    }
+    /// Case-insensitive and whitespace-normalized
+    pub fn relaxed() -> Self {
+        NameMatching {
+            case_insensitive: true,
+            normalize_whitespace: true,
+        }
+    }
+    /// name -> lookup key
+    pub fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let mut key = Cow::Borrowed(name);
+        if self.normalize_whitespace {
+            key = Cow::Owned(
+                key.split_whitespace().collect::<Vec<_>>().join(" "),
+            );
+        }
+        if self.case_insensitive {
+            key = Cow::Owned(key.to_lowercase());
+        }
+        key
+    }
+}
+
+/// Precomputed lookup of the position of a field by name
+#[derive(Debug, Clone)]
+pub struct HeaderMap {
+    matching: NameMatching,
+    positions: HashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl HeaderMap {
+    /// ⚠️  With duplicate names, the first position wins.
+    pub fn new(names: &[String], matching: NameMatching) -> Self {
+        let mut positions = HashMap::with_capacity(names.len());
+        for (idx, name) in names.iter().enumerate() {
+            positions
+                .entry(matching.normalize(name).into_owned())
+                .or_insert(idx);
+        }
+        HeaderMap {
+            matching,
+            positions,
+            names: names.to_vec(),
+        }
+    }
+    pub fn matching(&self) -> NameMatching {
+        self.matching
+    }
+    /// name -> position of the field
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.positions.get(&*self.matching.normalize(name)).copied()
+    }
+    /// name -> position of the field; the error lists the near matches
+    pub fn field_index(&self, name: &str) -> Result<usize, StructureError> {
+        self.get(name).ok_or_else(|| StructureError::UnknownField {
+            name: name.to_string(),
+            near: self.near(name),
+        })
+    }
+    /// The field names within an edit distance of a third of the length of the name (at least
+    /// one); closest first.
+    pub fn near(&self, name: &str) -> Vec<String> {
+        let relaxed = NameMatching::relaxed();
+        let target = relaxed.normalize(name);
+        let max_distance = (target.chars().count() / 3).max(1);
+
+        let mut near = self
+            .names
+            .iter()
+            .filter_map(|candidate| {
+                let distance =
+                    edit_distance(&target, &relaxed.normalize(candidate));
+                (distance <= max_distance).then_some((distance, candidate))
+            })
+            .collect::<Vec<_>>();
+        near.sort_by_key(|(distance, _)| *distance);
+        near.into_iter().map(|(_, name)| name.clone()).collect()
+    }
+}
+
+/// Levenshtein distance; two rows of the table.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut row = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + (ca != *cb) as usize;
+            row[j + 1] = substitute.min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut row);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["Id", "NRx Count", "Practitioner State", "nrx count"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lookup() {
+        let exact = HeaderMap::new(&names(), NameMatching::exact());
+        assert_eq!(exact.get("NRx Count"), Some(1));
+        assert_eq!(exact.get("nrx count"), Some(3));
+        assert_eq!(exact.get(" NRx  Count"), None);
+
+        let relaxed = HeaderMap::new(&names(), NameMatching::relaxed());
+        assert_eq!(relaxed.get(" nrx  COUNT "), Some(1));
+        assert_eq!(relaxed.get("practitioner\tstate"), Some(2));
+    }
+    #[test]
+    fn near_matches() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        let map = HeaderMap::new(&names(), NameMatching::exact());
+        match map.field_index("NRx Cnt") {
+            Err(StructureError::UnknownField { name, near }) => {
+                assert_eq!(name, "NRx Cnt");
+                assert_eq!(near, vec!["NRx Count", "nrx count"]);
+            }
+            other => panic!("unexpected: {:?}", other),
+        }
+        assert!(map.near("Zip").is_empty());
+    }
+}
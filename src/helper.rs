@@ -1,4 +1,5 @@
 use core::arch::x86_64::*;
+use std::borrow::Cow;
 
 #[derive(Debug)]
 pub struct ByteReport<'data> {
@@ -14,14 +15,14 @@ impl<'a> ByteReport<'a> {
             bytes: input,
         }
     }
-    /// a function; bytes -> &str for display input
-    pub fn _u8_as_str(input: &'a [u8]) -> &'a str {
-        std::str::from_utf8(input).expect("Fond invalid UTF-8")
+    /// a function; bytes -> str for display input (lossy; the data may be a legacy encoding)
+    pub fn _u8_as_str(input: &'a [u8]) -> Cow<'a, str> {
+        String::from_utf8_lossy(input)
     }
-    /// a function; bytes -> &str for display input
-    pub fn _m128_as_str(input: &'a [__m128]) -> &'a str {
+    /// a function; bytes -> str for display input (lossy)
+    pub fn _m128_as_str(input: &'a [__m128]) -> Cow<'a, str> {
         let tmp: &[u8] = unsafe { std::mem::transmute(input) };
-        std::str::from_utf8(tmp).expect("Fond invalid UTF-8")
+        String::from_utf8_lossy(tmp)
     }
 }
 
@@ -31,17 +32,15 @@ impl std::fmt::Display for ByteReport<'_> {
             0 => writeln!(f, "empty")?,
             x if x <= 1000 => {
                 let max = self.len - 1;
-                let first_1k =
-                    std::str::from_utf8(&self.bytes[0..max as usize]).expect("Invalid UTF-8");
+                let first_1k = String::from_utf8_lossy(&self.bytes[0..max as usize]);
                 writeln!(f, "num char: {}", self.len)?;
                 writeln!(f, "{}", first_1k)?;
             }
             _ => {
-                let first_1k = std::str::from_utf8(&self.bytes[0..1000]).expect("Invalid UTF-8");
-                let tail = std::str::from_utf8(
+                let first_1k = String::from_utf8_lossy(&self.bytes[0..1000]);
+                let tail = String::from_utf8_lossy(
                     &self.bytes[self.len as usize - 101..self.len as usize - 1],
-                )
-                .expect("Invalid UTF-8");
+                );
 
                 writeln!(f, "num char: {}", self.len)?;
                 writeln!(f, "{}...\n...{}", first_1k, tail)?;
@@ -1,6 +1,4 @@
 pub use memmap::Mmap;
-use std::fs::File;
-use std::time::Instant;
 
 #[cfg(target_arch = "x86")]
 use std::arch::x86::*;
@@ -18,21 +16,116 @@ mod avx;
 
 /// Generic support for the Stage1 processing of a CSV file
 pub(crate) mod stage1;
-pub use crate::stage1::StructureIndex;
+pub use crate::stage1::{NewLine, Slots, StructureIndex};
 
 pub mod record_source;
 pub use crate::record_source::{RecordSource, WithRecordSource};
 
+/// record iteration
+pub mod records;
+pub use crate::records::{ByteRecord, Record, Records};
+
+/// column views
+pub mod column;
+pub use crate::column::{Column, ColumnKey};
+
+/// numbers and booleans from the fields
+pub mod typed;
+pub use crate::typed::FromField;
+
+/// dates, year-months and timestamps from the fields
+pub mod temporal;
+pub use crate::temporal::{Date, DateFormat, DateTime, YearMonth};
+
+/// decimal and thousands separators, currency and percent symbols
+pub mod number_format;
+pub use crate::number_format::NumberFormat;
+
+/// column type inference
+pub mod schema;
+pub use crate::schema::{Bound, ColumnSchema, ColumnType, SampleSpec, Schema};
+
+/// value masks such as "10 digits" or "S9{9}"
+pub mod pattern;
+pub use crate::pattern::Pattern;
+
+/// contract checks against a declared schema
+pub mod validate;
+pub use crate::validate::{
+    ColumnReport, HeaderViolation, ValidationReport, Violation, ViolationKind,
+};
+
+/// the tokens of the missing values
+pub mod nulls;
+pub use crate::nulls::NullValues;
+
+/// serde deserialization of the records
+pub mod de;
+pub use crate::de::DeserializeRecords;
+
+/// Rust struct definitions from a schema
+pub mod codegen;
+pub use crate::codegen::rust_struct;
+
+/// CREATE TABLE, INSERT and COPY statements
+pub mod sql;
+pub use crate::sql::{SqlDump, SqlFlavor};
+
+/// frequency tables of the values
+pub mod counts;
+pub use crate::counts::ValueCounts;
+
 /// haystack
 pub mod reader;
 
+/// byte-at-a-time reference reader
+pub mod scalar;
+
 /// Start processing raw data
 mod structure;
 
 /// value/tape.rs
 pub mod tape;
+
+/// configure, then build a Tape
+pub mod builder;
+pub use crate::builder::{
+    Backend, HeaderMode, IndexWidth, TapeBuilder, Validation,
+};
 pub use crate::tape::{Header, Tape, TapeCore};
 
+/// record-range slices and column projections of a Tape
+pub mod view;
+pub use crate::view::{Projection, TapeView};
+
+/// reusable buffers to index many files
+pub mod parser;
+pub use crate::parser::Parser;
+
+/// guess the dialect and the header of the data
+pub mod sniff;
+pub use crate::sniff::{sniff, sniff_details, Sniff};
+
+/// push-based events; no index
+pub mod visitor;
+pub use crate::visitor::Visitor;
+
+/// protection from changes to the file hosting the data
+pub mod guard;
+pub use crate::guard::Protection;
+
+/// input encodings (UTF-8 and the single-byte legacy code pages)
+pub mod encoding;
+pub use crate::encoding::Encoding;
+
+/// delimiter, quote and escape
+pub mod dialect;
+pub use crate::dialect::{Dialect, Escape, Trim};
+
+/// field name -> field position
+pub mod header_map;
+pub use crate::header_map::{HeaderMap, NameMatching};
+
 /// error
 mod error;
 pub use crate::error::StructureError;
@@ -44,9 +137,6 @@ mod json_test;
 mod helper;
 pub use helper::ByteReport;
 
-// A tag that sometimes prefixes data sources to be ignored by the csv app.
-// const UTF8_BOM: &'static [u8] = b"\xef\xbb\xbf";
-
 #[allow(dead_code)]
 #[cfg(debug_assertions)]
 static PATH: &str = "./res/sample_rx.csv";
@@ -57,20 +147,25 @@ static PATH: &str = "/Users/edmund/Desktop/data/warfarin_NRx.csv";
 
 // 🚧  The lib factory
 //
-/// Create a Tape from a filename
-pub fn create(filename: &str) -> Result<Tape, StructureError> {
-    let now = Instant::now();
-
-    let file = File::open(filename)?;
-    let memmap = unsafe { Mmap::map(&file)? };
-    let header = tape::Header::new(&memmap);
-    let index = reader::read(&memmap);
-    let tape = TapeCore::create(memmap, index, header);
-    let tape = Tape::from_core(tape)?;
-
-    println!("Elapsed: {} seconds", now.elapsed().as_secs_f64());
+/// Create a Tape from a filename. The encoding describes the bytes in the file; the fields are
+/// transcoded to UTF-8 on access.
+pub fn create(
+    filename: &str,
+    encoding: Encoding,
+) -> Result<Tape, StructureError> {
+    create_with_protection(filename, encoding, Protection::None)
+}
 
-    Ok(tape)
+/// Create a Tape from a filename, with a policy to protect the Tape from changes to the file.
+pub fn create_with_protection(
+    filename: &str,
+    encoding: Encoding,
+    protection: Protection,
+) -> Result<Tape, StructureError> {
+    TapeBuilder::new()
+        .encoding(encoding)
+        .protection(protection)
+        .build_from_path(filename)
 }
 
 /*
@@ -113,11 +208,114 @@ pub fn run() -> io::Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use crate::{
+        create, create_with_protection, Encoding, NameMatching, Protection,
+        RecordSource, StructureError, TapeBuilder,
+    };
+    use std::sync::Arc;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
     #[test]
+    fn windows_1252_fields() {
+        let tape =
+            create("./res/sample_cp1252.csv", Encoding::Windows1252).unwrap();
+        assert_eq!(tape.header()[1], "Caf\u{e9}");
+        let tape = &tape;
+        assert_eq!(
+            tape.seek_field_decoded(0, 1).unwrap().unwrap(),
+            "cr\u{e8}me br\u{fb}l\u{e9}e"
+        );
+        assert_eq!(
+            tape.seek_field_decoded(1, 2).unwrap().unwrap(),
+            "\u{20ac}4"
+        );
+        assert!(matches!(
+            tape.seek_field_decoded(1, 0).unwrap().unwrap(),
+            std::borrow::Cow::Borrowed("Oslo")
+        ));
+    }
+    #[test]
+    fn field_by_name() {
+        let mut tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        assert_eq!(tape.field_index("NRx Count").unwrap(), 7);
+        assert_eq!(
+            tape.seek_field_by_name(6, "Payment Type Group")
+                .unwrap()
+                .unwrap(),
+            "CASH,IT"
+        );
+        assert!(matches!(
+            tape.field_index("nrx count"),
+            Err(StructureError::UnknownField { near, .. }) if near == ["NRx Count"]
+        ));
+        tape.set_name_matching(NameMatching::relaxed());
+        assert_eq!(tape.field_index(" nrx  count").unwrap(), 7);
+    }
+    #[test]
+    fn in_memory_sources() {
+        static DATA: &[u8] = b"id,name\n1,\"a,b\"\n2,c\n3,d\n";
+        let builder = TapeBuilder::new();
+        let tapes = vec![
+            builder.build_from_bytes(DATA).unwrap(),
+            builder.build_from_bytes(DATA.to_vec()).unwrap(),
+            builder.build_from_bytes(Arc::<[u8]>::from(DATA)).unwrap(),
+        ];
+        for tape in &tapes {
+            assert_eq!(
+                tape.seek_field_by_name(0, "name").unwrap().unwrap(),
+                "a,b"
+            );
+            assert_eq!(tape.chunks(2).unwrap().len(), 2);
+            assert_eq!(tape.as_records().len(), 3);
+        }
+        let clone = tapes[1].clone();
+        assert_eq!(clone.bytes().as_ptr(), tapes[1].bytes().as_ptr());
+        assert_eq!((&clone).seek_record(2).unwrap(), Some("3,d"));
+    }
+    #[test]
+    fn protection_copy() {
+        let path = "./res/sample_rx.csv";
+        let mapped = create(path, Encoding::Utf8).unwrap();
+        let copied =
+            create_with_protection(path, Encoding::Utf8, Protection::Copy)
+                .unwrap();
+        assert_eq!(mapped.index().len(), copied.index().len());
+        assert_eq!(
+            (&mapped).seek_record(5).unwrap(),
+            (&copied).seek_record(5).unwrap()
+        );
+        assert!(!copied.is_stale());
+        assert!(copied.verify().is_ok());
+    }
+    #[test]
+    fn stale_and_locked() {
+        let path = std::env::temp_dir().join("csv_simd_stale_test.csv");
+        let rows = "a,b\n".to_string() + &"1,2\n".repeat(40);
+        std::fs::write(&path, &rows).unwrap();
+        let filename = path.to_str().unwrap();
+
+        let tape =
+            create_with_protection(filename, Encoding::Utf8, Protection::Lock)
+                .unwrap();
+        assert!(tape.is_locked());
+        assert!(!tape.is_stale());
+        // an exclusive lock must wait for the tape
+        let other = std::fs::File::open(&path).unwrap();
+        assert!(other.try_lock().is_err());
+        drop(tape);
+        assert!(other.try_lock().is_ok());
+        other.unlock().unwrap();
+
+        let tape = create(filename, Encoding::Utf8).unwrap();
+        std::fs::write(&path, &rows[..rows.len() - 4]).unwrap();
+        assert!(tape.is_stale());
+        assert!(tape.ensure_fresh().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
     fn binary_manipulations() {
         // 📚 ...isolate the lowest set bit
         // create an index
@@ -0,0 +1,115 @@
+///
+/// Missing values
+///
+/// A field is null when its value is empty, or one of the tokens of the feed (`NA`, `NULL`,
+/// `\N`, `-`...).  The tokens are compared with the value (quotes removed, escapes resolved,
+/// spaces trimmed when the dialect trims the fields) in the bytes of the source.
+///
+/// ⚠️  `""` is null unless the dialect keeps a quoted empty string as a value (see
+///    `Dialect::quoted_empty_is_value`).
+///
+use crate::dialect::Dialect;
+
+/// The tokens that mean "missing"
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NullValues {
+    tokens: Vec<Vec<u8>>,
+}
+
+impl NullValues {
+    pub fn new<T: AsRef<[u8]>>(tokens: impl IntoIterator<Item = T>) -> Self {
+        NullValues {
+            tokens: tokens
+                .into_iter()
+                .map(|token| token.as_ref().to_vec())
+                .collect(),
+        }
+    }
+    /// NA, N/A, NULL, null, \N and -
+    pub fn common() -> Self {
+        NullValues::new(["NA", "N/A", "NULL", "null", "\\N", "-"])
+    }
+    pub fn tokens(&self) -> impl Iterator<Item = &[u8]> {
+        self.tokens.iter().map(|token| token.as_slice())
+    }
+    /// Is the raw field missing
+    pub fn is_null(&self, raw: &[u8], dialect: &Dialect) -> bool {
+        let value = dialect.unescape(raw);
+        let value = match dialect.trim.fields() {
+            true => value.trim_ascii(),
+            false => &value,
+        };
+        if value.is_empty() {
+            let raw = match dialect.trim.fields() {
+                true => raw.trim_ascii(),
+                false => raw,
+            };
+            return !(dialect.quoted_empty_is_value && dialect.is_quoted(raw));
+        }
+        self.tokens.iter().any(|token| token.as_slice() == value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Trim;
+    use crate::{RecordSource, TapeBuilder};
+
+    #[test]
+    fn tokens() {
+        let nulls = NullValues::common();
+        let mut dialect = Dialect::default();
+        assert!(nulls.is_null(b"", &dialect));
+        assert!(nulls.is_null(b"\"\"", &dialect));
+        assert!(nulls.is_null(b"\"\\N\"", &dialect));
+        assert!(!nulls.is_null(b"NaN", &dialect));
+        assert!(!nulls.is_null(b" ", &dialect));
+        assert!(!nulls.is_null(b" NA ", &dialect));
+        dialect.trim = Trim::Fields;
+        assert!(nulls.is_null(b" ", &dialect));
+        assert!(nulls.is_null(b" NA ", &dialect));
+        assert!(nulls.is_null(b" \"NA\" ", &dialect));
+        dialect.quoted_empty_is_value = true;
+        assert!(!nulls.is_null(b"\"\"", &dialect));
+        assert!(nulls.is_null(b"", &dialect));
+    }
+    #[test]
+    fn accessors() {
+        let data = b"id,count,note\n1,NA,\"\"\n2,-,\n3,4,x\n";
+        let dialect = Dialect {
+            quoted_empty_is_value: true,
+            ..Dialect::default()
+        };
+        let mut tape = TapeBuilder::new()
+            .dialect(dialect)
+            .null_values(NullValues::common())
+            .build_from_bytes(&data[..])
+            .unwrap();
+        let record = tape.as_records().next().unwrap();
+        assert!(record.is_null(1));
+        assert_eq!(record.get_i64(1).unwrap(), None);
+        assert_eq!(record.get_value(2).as_deref(), Some(""));
+        assert_eq!(record.get_value(5), None);
+
+        let counts = tape.column("count").unwrap();
+        let mut values = Vec::new();
+        counts.parse_i64_into(&mut values).unwrap();
+        assert_eq!(values, vec![None, None, Some(4)]);
+        let notes = tape.column("note").unwrap();
+        let notes = notes.nullable_values().collect::<Vec<_>>();
+        assert_eq!(notes, vec![Some("".into()), None, Some("x".into())]);
+
+        let no_tokens = NullValues::default();
+        let counts = tape.column(1_usize).unwrap().with_null_values(&no_tokens);
+        assert!(counts.get_i64(0).is_err());
+        assert_eq!(counts.get_value(1).as_deref(), Some("-"));
+
+        assert_eq!(tape.seek_value(0, 1).unwrap(), None);
+        assert_eq!(tape.seek_field_by_name(0, "count").unwrap(), None);
+        assert_eq!(record.value(1).as_deref(), Some("NA"));
+        tape.set_null_values(NullValues::default());
+        assert_eq!(tape.seek_value(0, 1).unwrap().as_deref(), Some("NA"));
+        assert_eq!((&tape).seek_field(0, 1).unwrap(), Some("NA"));
+    }
+}
@@ -0,0 +1,255 @@
+///
+/// How the numbers are written
+///
+/// Exports for a locale write `1.234,56` (decimal comma), `€ 1 234,56`, `12,5 %` or `(42)` for a
+/// negative amount.  A `NumberFormat` rewrites such a value to the plain form (`-1234.56`)
+/// before it is parsed by the typed accessors (see `FromField`).
+///
+/// 🔑 The default format is plain; the values are parsed as they are (no copy).
+///
+use std::borrow::Cow;
+
+/// The separators and symbols of the numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// '.' or ','
+    pub decimal: u8,
+    /// Ignored between the digits; e.g. ',' '.' ' ' or '\''
+    pub thousands: Option<u8>,
+    /// Ignored before or after the number; e.g. '€' or '$'
+    pub currency: Option<char>,
+    /// A trailing '%' divides a float by 100; an integer percent is invalid
+    pub percent: bool,
+    /// `(42)` is -42
+    pub parenthesized_negatives: bool,
+    /// `NaN`, `inf` and `infinity` are floats to the schema inference (see `Tape::infer_schema`)
+    pub non_finite: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimal: b'.',
+            thousands: None,
+            currency: None,
+            percent: false,
+            parenthesized_negatives: false,
+            non_finite: false,
+        }
+    }
+}
+
+/// A value rewritten to the plain form
+pub(crate) struct PlainNumber<'a> {
+    pub(crate) text: Cow<'a, [u8]>,
+    pub(crate) percent: bool,
+}
+
+impl NumberFormat {
+    /// 1,234.56
+    pub fn us() -> Self {
+        NumberFormat {
+            thousands: Some(b','),
+            ..NumberFormat::default()
+        }
+    }
+    /// 1.234,56
+    pub fn european() -> Self {
+        NumberFormat {
+            decimal: b',',
+            thousands: Some(b'.'),
+            ..NumberFormat::default()
+        }
+    }
+    pub fn currency(self, symbol: char) -> Self {
+        NumberFormat {
+            currency: Some(symbol),
+            ..self
+        }
+    }
+    pub fn percent(self) -> Self {
+        NumberFormat {
+            percent: true,
+            ..self
+        }
+    }
+    pub fn parenthesized_negatives(self) -> Self {
+        NumberFormat {
+            parenthesized_negatives: true,
+            ..self
+        }
+    }
+    pub fn non_finite(self) -> Self {
+        NumberFormat {
+            non_finite: true,
+            ..self
+        }
+    }
+    /// The values are parsed as they are
+    pub fn is_plain(&self) -> bool {
+        // the inference setting does not rewrite a value
+        NumberFormat {
+            non_finite: false,
+            ..*self
+        } == NumberFormat::default()
+    }
+    /// trimmed value -> `[-]digits[.digits]`; None when a symbol is misplaced
+    pub(crate) fn plain<'a>(&self, value: &'a [u8]) -> Option<PlainNumber<'a>> {
+        if self.is_plain() {
+            return Some(PlainNumber {
+                text: Cow::Borrowed(value),
+                percent: false,
+            });
+        }
+        let mut negative = false;
+        let mut value = value;
+        if self.parenthesized_negatives {
+            if let Some(inner) =
+                value.strip_prefix(b"(").and_then(|v| v.strip_suffix(b")"))
+            {
+                negative = true;
+                value = inner.trim_ascii();
+            }
+        }
+        let mut percent = false;
+        if self.percent {
+            if let Some(rest) = value.strip_suffix(b"%") {
+                percent = true;
+                value = rest.trim_ascii_end();
+            }
+        }
+        // the sign is either side of the currency symbol: -€5, €-5
+        value = self.strip_sign(value, &mut negative);
+        value = self.strip_currency(value);
+        value = self.strip_sign(value, &mut negative);
+
+        if !self.is_grouped(value) {
+            return None;
+        }
+        let mut text = Vec::with_capacity(value.len() + 1);
+        if negative {
+            text.push(b'-');
+        }
+        for &byte in value {
+            match byte {
+                _ if Some(byte) == self.thousands => (),
+                _ if byte == self.decimal => text.push(b'.'),
+                b'.' | b',' | b'+' | b'-' => return None,
+                _ => text.push(byte),
+            }
+        }
+        Some(PlainNumber {
+            text: Cow::Owned(text),
+            percent,
+        })
+    }
+    /// The thousands separator is only between groups of three digits, left of the decimal
+    /// separator: 1.234.567,5 (not 1.5, 12.34 or .5)
+    fn is_grouped(&self, value: &[u8]) -> bool {
+        let separator = match self.thousands {
+            Some(separator) => separator,
+            None => return true,
+        };
+        let end = value
+            .iter()
+            .position(|&byte| byte == self.decimal)
+            .unwrap_or(value.len());
+        let (integer, fraction) = value.split_at(end);
+        if fraction.contains(&separator) {
+            return false;
+        }
+        if !integer.contains(&separator) {
+            return true;
+        }
+        let mut groups = integer.split(|&byte| byte == separator);
+        let first = groups.next().unwrap_or_default();
+        (1..=3).contains(&first.len()) && groups.all(|group| group.len() == 3)
+    }
+    fn strip_sign<'a>(&self, value: &'a [u8], negative: &mut bool) -> &'a [u8] {
+        match value.split_first() {
+            Some((b'-', rest)) if !*negative => {
+                *negative = true;
+                rest.trim_ascii_start()
+            }
+            Some((b'+', rest)) => rest.trim_ascii_start(),
+            _ => value,
+        }
+    }
+    fn strip_currency<'a>(&self, value: &'a [u8]) -> &'a [u8] {
+        let symbol = match self.currency {
+            Some(symbol) => symbol,
+            None => return value,
+        };
+        let mut buffer = [0; 4];
+        let symbol = symbol.encode_utf8(&mut buffer).as_bytes();
+        if let Some(rest) = value.strip_prefix(symbol) {
+            rest.trim_ascii_start()
+        } else if let Some(rest) = value.strip_suffix(symbol) {
+            rest.trim_ascii_end()
+        } else {
+            value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromField, TapeBuilder};
+
+    #[test]
+    fn european() {
+        let format = NumberFormat::european()
+            .currency('€')
+            .percent()
+            .parenthesized_negatives();
+        let parse =
+            |value: &str| f64::from_formatted(value.as_bytes(), &format);
+        assert_eq!(parse("1.234,56"), Some(1234.56));
+        assert_eq!(parse("€ 1.234,5"), Some(1234.5));
+        assert_eq!(parse("-1.234 €"), Some(-1234.0));
+        assert_eq!(parse("€-3"), Some(-3.0));
+        assert_eq!(parse("(42,5)"), Some(-42.5));
+        assert_eq!(parse("12,5 %"), Some(0.125));
+        assert_eq!(parse("1,2,3"), None);
+        assert_eq!(parse("--3"), None);
+        assert_eq!(parse("1.234.567,5"), Some(1234567.5));
+        assert_eq!(parse("1.5"), None);
+        assert_eq!(parse("1.2.3"), None);
+        assert_eq!(parse("12.34"), None);
+        assert_eq!(parse("1234.567"), None);
+        assert_eq!(parse(".123"), None);
+        assert_eq!(parse("1,5.000"), None);
+        let us = |value: &str| {
+            f64::from_formatted(value.as_bytes(), &NumberFormat::us())
+        };
+        assert_eq!(us(",5"), None);
+        assert_eq!(us("1,5"), None);
+        assert_eq!(us("12,345,678.9"), Some(12345678.9));
+        assert_eq!(i64::from_formatted(b"(1.234)", &format), Some(-1234));
+        assert_eq!(i64::from_formatted(b"12 %", &format), None);
+        assert_eq!(u64::from_formatted(b"-5", &format), None);
+        assert_eq!(
+            f64::from_formatted(b"1,234.5", &NumberFormat::us()),
+            Some(1234.5)
+        );
+    }
+    #[test]
+    fn tape_format() {
+        let data = "city;total\nOslo;\"1.234,50\"\nBergen;\u{20ac}12\n";
+        let mut tape = TapeBuilder::new()
+            .delimiter(b';')
+            .number_format(NumberFormat::european().currency('€'))
+            .build_from_bytes(data.as_bytes().to_vec())
+            .unwrap();
+        let totals = tape.column("total").unwrap();
+        assert_eq!(totals.get_f64(0).unwrap(), Some(1234.5));
+        assert_eq!(totals.get_i64(1).unwrap(), Some(12));
+        let plain = totals.with_number_format(NumberFormat::default());
+        assert!(plain.get_f64(0).is_err());
+
+        tape.set_number_format(NumberFormat::default());
+        let record = tape.as_records().next().unwrap();
+        assert!(record.get_f64(1).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+///
+/// Index file after file without re-allocating
+///
+/// 📚 simdjson: `from_slice_with_buffers`; the parser owns the buffers, the document borrows them.
+///
+/// The Parser owns:
+/// * the index accumulator; pre-sized with `reader::estimate_len`, and retains its capacity
+/// * a scratch buffer for the bytes read into memory (`Protection::Copy`, `parse_reader`)
+///
+/// A Tape takes ownership of the buffers; `recycle` hands them back.  The `*_with` methods
+/// lend the Tape to a closure, then recycle it.
+///
+/// ⚠️  A buffer shared with a clone of the Tape cannot be recycled; it is dropped.
+///
+use std::io::Read;
+use std::path::Path;
+
+use crate::builder::TapeBuilder;
+use crate::error::StructureError;
+use crate::tape::{DataBytes, Tape};
+
+/// Reusable index and scratch buffers
+#[derive(Debug, Default)]
+pub struct Parser {
+    builder: TapeBuilder,
+    index: Vec<usize>,
+    scratch: Vec<u8>,
+}
+
+impl Parser {
+    pub fn new(builder: TapeBuilder) -> Self {
+        Parser {
+            builder,
+            ..Parser::default()
+        }
+    }
+    /// The capacity of the buffers: (index slots, scratch bytes)
+    pub fn capacity(&self) -> (usize, usize) {
+        (self.index.capacity(), self.scratch.capacity())
+    }
+    pub fn parse_path(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<Tape, StructureError> {
+        let path = path.as_ref();
+        let scratch = std::mem::take(&mut self.scratch);
+        let (bytes, stamp, lock) = self.builder.host(path, scratch)?;
+        Ok(self.parse(bytes)?.guarded(stamp, lock))
+    }
+    pub fn parse_bytes(
+        &mut self,
+        bytes: impl Into<DataBytes>,
+    ) -> Result<Tape, StructureError> {
+        self.parse(bytes.into())
+    }
+    /// Read to the end into the scratch buffer
+    pub fn parse_reader(
+        &mut self,
+        mut reader: impl Read,
+    ) -> Result<Tape, StructureError> {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        reader.read_to_end(&mut scratch)?;
+        self.parse(scratch.into())
+    }
+    /// Lend the Tape of a file to `f`, then recycle it
+    pub fn parse_path_with<R>(
+        &mut self,
+        path: impl AsRef<Path>,
+        f: impl FnOnce(&Tape) -> R,
+    ) -> Result<R, StructureError> {
+        let tape = self.parse_path(path)?;
+        let result = f(&tape);
+        self.recycle(tape);
+        Ok(result)
+    }
+    /// Lend the Tape of the read data to `f`, then recycle it
+    pub fn parse_reader_with<R>(
+        &mut self,
+        reader: impl Read,
+        f: impl FnOnce(&Tape) -> R,
+    ) -> Result<R, StructureError> {
+        let tape = self.parse_reader(reader)?;
+        let result = f(&tape);
+        self.recycle(tape);
+        Ok(result)
+    }
+    /// Take back the buffers of a Tape created by this (or another) Parser
+    pub fn recycle(&mut self, tape: Tape) {
+        let (bytes, index) = tape.into_buffers();
+        if let Some(index) = index {
+            if index.capacity() > self.index.capacity() {
+                self.index = index;
+            }
+        }
+        if let Some(bytes) = bytes {
+            if bytes.capacity() > self.scratch.capacity() {
+                self.scratch = bytes;
+            }
+        }
+    }
+    fn parse(&mut self, bytes: DataBytes) -> Result<Tape, StructureError> {
+        let index = std::mem::take(&mut self.index);
+        self.builder.build_into(bytes, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Protection, RecordSource, StructureIndex};
+
+    #[test]
+    fn recycled_buffers() {
+        let mut parser =
+            Parser::new(TapeBuilder::new().protection(Protection::Copy));
+        let first = parser
+            .parse_path_with("./res/sample_rx.csv", |tape| {
+                tape.as_records().len()
+            })
+            .unwrap();
+        assert_eq!(first, 7);
+        let (index_cap, scratch_cap) = parser.capacity();
+        assert!(index_cap > 0 && scratch_cap > 0);
+
+        let index_ptr = parser.index.as_ptr();
+        let value = parser
+            .parse_reader_with(&b"a,b\n1,2\n"[..], |tape| {
+                let ptr = match tape.index() {
+                    StructureIndex::Usize(index) => {
+                        index.as_ptr() as *const usize
+                    }
+                    StructureIndex::U32(_) => std::ptr::null(),
+                };
+                (ptr, tape.index().len())
+            })
+            .unwrap();
+        // the smaller input reused the allocation of the first
+        assert_eq!(value, (index_ptr, 5));
+        assert_eq!(parser.capacity().0, index_cap);
+
+        let tape = parser.parse_bytes(&b"x\n1\n"[..]).unwrap();
+        let clone = tape.clone();
+        parser.recycle(tape);
+        assert_eq!(parser.capacity().0, 0);
+        assert_eq!((&clone).seek_record(0).unwrap(), Some("1"));
+    }
+}
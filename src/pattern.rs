@@ -0,0 +1,247 @@
+///
+/// Value patterns without regex
+///
+/// A mask, one class per character of the value:
+///
+///   9  a digit          A  a letter        X  a letter or a digit
+///   ?  any character    \c the character c anything else is itself
+///
+/// followed by an optional repeat: `{n}` or `{min,max}`.  Or a phrase: `10 digits`,
+/// `1-5 letters`, `8 characters`.
+///
+///   "S9{9}"      S207916573
+///   "99999"      33166
+///   "9999-99"    2016-09
+///
+/// 🔑 The value matches as a whole; it is trimmed first.
+///
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::StructureError;
+
+/// A parsed mask or phrase; serialized as its source
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pattern {
+    source: String,
+    elements: Vec<Element>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Digit,
+    Letter,
+    Alphanumeric,
+    Any,
+    Literal(char),
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Class::Digit => c.is_ascii_digit(),
+            Class::Letter => c.is_alphabetic(),
+            Class::Alphanumeric => c.is_alphanumeric(),
+            Class::Any => true,
+            Class::Literal(literal) => c == *literal,
+        }
+    }
+}
+
+/// A class repeated min..=max times
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Element {
+    class: Class,
+    min: usize,
+    max: usize,
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Pattern, StructureError> {
+        let elements = match phrase(source) {
+            Some(element) => vec![element?],
+            None => mask(source)?,
+        };
+        Ok(Pattern {
+            source: source.to_string(),
+            elements,
+        })
+    }
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+    pub fn is_match(&self, value: &str) -> bool {
+        let chars = value.trim().chars().collect::<Vec<_>>();
+        is_match(&self.elements, &chars)
+    }
+}
+
+/// The first element takes the most characters that leave a match for the rest
+fn is_match(elements: &[Element], chars: &[char]) -> bool {
+    match elements.split_first() {
+        None => chars.is_empty(),
+        Some((element, rest)) => {
+            let run = chars
+                .iter()
+                .take(element.max)
+                .take_while(|&&c| element.class.matches(c))
+                .count();
+            run >= element.min
+                && (element.min..=run)
+                    .rev()
+                    .any(|len| is_match(rest, &chars[len..]))
+        }
+    }
+}
+
+fn invalid(reason: String) -> StructureError {
+    StructureError::InvalidOption {
+        option: "pattern",
+        reason,
+    }
+}
+
+/// "10 digits", "1-5 letters"; None when not a phrase
+fn phrase(source: &str) -> Option<Result<Element, StructureError>> {
+    let mut words = source.split_whitespace();
+    let (count, unit) = match (words.next(), words.next(), words.next()) {
+        (Some(count), Some(unit), None) => (count, unit),
+        _ => return None,
+    };
+    let class = match unit {
+        "digit" | "digits" => Class::Digit,
+        "letter" | "letters" => Class::Letter,
+        "character" | "characters" => Class::Any,
+        _ => return None,
+    };
+    let (min, max) = match count.split_once('-') {
+        Some((min, max)) => (min.parse().ok()?, max.parse().ok()?),
+        None => {
+            let count = count.parse().ok()?;
+            (count, count)
+        }
+    };
+    Some(element(class, min, max))
+}
+
+fn element(
+    class: Class,
+    min: usize,
+    max: usize,
+) -> Result<Element, StructureError> {
+    match min <= max {
+        true => Ok(Element { class, min, max }),
+        false => Err(invalid(format!("repeat {{{},{}}}", min, max))),
+    }
+}
+
+fn mask(source: &str) -> Result<Vec<Element>, StructureError> {
+    let mut elements: Vec<Element> = Vec::new();
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        let class = match c {
+            '9' => Class::Digit,
+            'A' => Class::Letter,
+            'X' => Class::Alphanumeric,
+            '?' => Class::Any,
+            '\\' => match chars.next() {
+                Some(c) => Class::Literal(c),
+                None => return Err(invalid("a trailing \\".to_string())),
+            },
+            '{' => {
+                let last = elements.pop().ok_or_else(|| {
+                    invalid("a repeat without a class".to_string())
+                })?;
+                let mut repeat = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => repeat.push(c),
+                        None => {
+                            return Err(invalid(format!(
+                                "an unterminated repeat {{{}",
+                                repeat
+                            )))
+                        }
+                    }
+                }
+                let count = |count: &str| {
+                    count
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| invalid(format!("repeat {{{}}}", repeat)))
+                };
+                let (min, max) = match repeat.split_once(',') {
+                    Some((min, max)) => (count(min)?, count(max)?),
+                    None => (count(&repeat)?, count(&repeat)?),
+                };
+                if last.min != 1 || last.max != 1 {
+                    return Err(invalid(format!("repeat {{{}}}", repeat)));
+                }
+                elements.push(element(last.class, min, max)?);
+                continue;
+            }
+            _ => Class::Literal(c),
+        };
+        elements.push(Element {
+            class,
+            min: 1,
+            max: 1,
+        });
+    }
+    Ok(elements)
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = StructureError;
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Pattern::new(&source)
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(pattern: Pattern) -> String {
+        pattern.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_and_phrases() {
+        let npi = Pattern::new("10 digits").unwrap();
+        assert!(npi.is_match("1003002813"));
+        assert!(!npi.is_match("107"));
+        let spi = Pattern::new("S9{1,9}").unwrap();
+        assert!(spi.is_match("S207916573"));
+        assert!(spi.is_match(" S3 "));
+        assert!(!spi.is_match("S"));
+        let month = Pattern::new("9999-99").unwrap();
+        assert!(month.is_match("2016-09"));
+        assert!(!month.is_match("2016-9"));
+        let code = Pattern::new("A{1,3}?{0,5}\\9").unwrap();
+        assert!(code.is_match("FL-339"));
+        assert!(!code.is_match("FL-33"));
+        assert_eq!(
+            Pattern::new("2-4 letters").unwrap().as_str(),
+            "2-4 letters"
+        );
+
+        assert!(Pattern::new("{3}").is_err());
+        assert!(Pattern::new("9{5,2}").is_err());
+        assert!(Pattern::new("9{x}").is_err());
+        assert!(Pattern::new("9{3").is_err());
+        assert!(Pattern::new("9{").is_err());
+    }
+}
@@ -11,15 +11,19 @@
 ///
 use core::arch::x86_64::*;
 use std::mem;
+use std::ops::ControlFlow;
 
 use bytemuck::allocation::cast_vec;
-use memmap::Mmap;
 
 use crate::avx::stage1::SimdInput;
 use crate::avx::stage1::INPUT_LENGTH;
+use crate::dialect::Dialect;
+use crate::error::StructureError;
+#[cfg(feature = "trace")]
 use crate::helper::ByteReport;
+use crate::scalar;
 use crate::stage1::StructureIndex;
-use crate::stage1::{SimdInputFragment, Stage1};
+use crate::stage1::{Classifier, SimdInputFragment, Stage1};
 // use crate::reader;
 
 /// Non-core
@@ -55,6 +59,7 @@ pub fn is_ascii(s: &[u8]) -> bool {
         || len < align_offset
         || USIZE_SIZE < mem::align_of::<usize>()
     {
+        #[cfg(feature = "trace")]
         println!("Too small to vecterize: {:?}", &s);
         return s.iter().all(|b| b.is_ascii());
     }
@@ -147,10 +152,63 @@ pub fn is_ascii(s: &[u8]) -> bool {
 /// * platform-specific vectorized computation
 /// * more of a scalar approach
 ///
-pub fn read(memmap: &Mmap) -> StructureIndex {
+pub fn read(bytes: &[u8]) -> StructureIndex {
+    read_with(bytes, &Dialect::default())
+}
+
+/// Read with the delimiter and quote of a dialect.
+///
+/// ⚠️  The vectorized reader does not support an escape other than `""`; see `scalar::read_with`.
+pub fn read_with(bytes: &[u8], dialect: &Dialect) -> StructureIndex {
+    read_into(bytes, dialect, Vec::new())
+}
+
+/// Read into a recycled accumulator (see `Parser`); the previous content is cleared, the
+/// capacity is retained, and topped-up with the `estimate_len` of the index.
+pub(crate) fn read_into(
+    bytes: &[u8],
+    dialect: &Dialect,
+    mut struct_acc: Vec<usize>,
+) -> StructureIndex {
+    // initialize the structure index with zero as the first value
+    struct_acc.clear();
+    struct_acc.reserve(estimate_len(bytes, dialect));
+    struct_acc.push(0);
+    let mut array_idx = 1; // struct_acc.len()
+
+    for_each_block(bytes, dialect, |set_bits, codepoint_cnt| {
+        SimdInput::crush_set_bits(
+            &mut struct_acc,
+            set_bits,
+            codepoint_cnt,
+            &mut array_idx,
+        );
+        ControlFlow::Continue(())
+    });
+
+    // 🎉 The index result!
+    #[cfg(feature = "trace")]
+    {
+        println!("🎉 index:\n{:?}", struct_acc);
+        println!("len: {:?}", struct_acc.len());
+    }
+    StructureIndex::Usize(cast_vec(struct_acc))
+}
+
+/// Drive the Stage1 over the bytes, 64 bytes at a time.  Reports the structure bitmap of each
+/// block with the position of its first byte: `on_block(set_bits, codepoint_cnt)`.  Stops when
+/// `on_block` breaks.
+///
+/// 🔑 The bitmaps are reported in order; a consumer that does not accumulate the positions
+///    (e.g., `visitor::visit`) runs in constant memory.
+pub(crate) fn for_each_block(
+    bytes: &[u8],
+    dialect: &Dialect,
+    mut on_block: impl FnMut(u64, usize) -> ControlFlow<()>,
+) {
+    let classifier = Classifier::new(dialect);
     // inventory of bytes
-    let bytes = memmap;
-    #[cfg(debug_assertions)]
+    #[cfg(feature = "trace")]
     {
         println!(
             "---------------------------------------------------------------"
@@ -180,7 +238,7 @@ pub fn read(memmap: &Mmap) -> StructureIndex {
     let (head_u8, body_vectors, tail_u8) =
         unsafe { bytes.align_to::<__m128>() };
     // Report memory fragments
-    #[cfg(debug_assertions)]
+    #[cfg(feature = "trace")]
     {
         println!(
             "---------------------------------------------------------------"
@@ -212,43 +270,45 @@ pub fn read(memmap: &Mmap) -> StructureIndex {
     let mut simdinput_cnt = 0;
     let mut codepoint_cnt = 0;
     let mut set_bits: u64 = 0;
-    // initialize the structure index with zero as the first value
-    let mut struct_acc = vec![0];
-    let mut array_idx = 1; // struct_acc.len()
     let mut inside_str = 0;
 
-    let iter_cnt = if num_vectors < INPUT_LENGTH {
-        0
-    } else {
-        num_vectors - INPUT_LENGTH
-    };
+    // 🔑 A memory map is page-aligned; other hosts (e.g., a Vec) may not be.
+    //    Process the unaligned head as a padded SimdInput, then continue the
+    //    count of code-points from the end of the head.
+    if !head_u8.is_empty() {
+        let padded_head = SimdInput::new_with_padding(&[], head_u8);
+        padded_head.structure(&classifier, &mut set_bits, &mut inside_str);
+        if on_block(set_bits, codepoint_cnt).is_break() {
+            return;
+        }
+        codepoint_cnt = head_u8.len();
+    }
 
-    #[cfg(debug_assertions)]
-    println!("⚠️  num_vectors: {} len {}", num_vectors, iter_cnt);
+    #[cfg(feature = "trace")]
+    println!("⚠️  num_vectors: {}", num_vectors);
 
-    while simdinput_cnt <= iter_cnt {
+    // ⚠️  Only load complete SimdInputs; with fewer than INPUT_LENGTH vectors
+    //    remaining, the vectors are processed with the padded tail.
+    while simdinput_cnt + INPUT_LENGTH <= num_vectors {
         // load a 64-byte slice of the data into the registers
         let input = unsafe {
             SimdInput::new(body_vectors.get_unchecked(simdinput_cnt as usize..))
         };
 
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "trace")]
         input.show();
 
         // transform the 64-bytes -> 64-bit structure
-        input.structure(&mut set_bits, &mut inside_str);
-        SimdInput::crush_set_bits(
-            &mut struct_acc,
-            set_bits,
-            codepoint_cnt,
-            &mut array_idx,
-        );
+        input.structure(&classifier, &mut set_bits, &mut inside_str);
+        if on_block(set_bits, codepoint_cnt).is_break() {
+            return;
+        }
 
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "trace")]
         {
             println!(
                 "🟢 simdinput_cnt: {} of len: {}",
-                simdinput_cnt, iter_cnt
+                simdinput_cnt, num_vectors
             );
             input.show();
         }
@@ -257,17 +317,19 @@ pub fn read(memmap: &Mmap) -> StructureIndex {
         codepoint_cnt += 64; // codepoint => setbits
     }
 
-    #[cfg(debug_assertions)]
     debug_assert_eq!(
         num_vectors - simdinput_cnt,
         num_vectors % INPUT_LENGTH,
         "The input vectors are not being processed as expected"
     );
-    println!("---------------------------------------------------------------------------------");
-    println!(
-        "🏁 simdinput_cnt: {} of vectors: {}",
-        simdinput_cnt, num_vectors
-    );
+    #[cfg(feature = "trace")]
+    {
+        println!("---------------------------------------------------------------------------------");
+        println!(
+            "🏁 simdinput_cnt: {} of vectors: {}",
+            simdinput_cnt, num_vectors
+        );
+    }
     // 🔑 Maintain continuity of the memory whilst padding to SimdInput
     // load the remaining 128
     // load tail (0-16 x u8)
@@ -281,30 +343,98 @@ pub fn read(memmap: &Mmap) -> StructureIndex {
     // reset the set_bits b/c the logic relies on any unused
     // memory be set to zero.
     set_bits = 0;
-    padded_input.structure(&mut set_bits, &mut inside_str);
-    SimdInput::crush_set_bits(
-        &mut struct_acc,
-        set_bits,
-        codepoint_cnt,
-        &mut array_idx,
-    );
+    padded_input.structure(&classifier, &mut set_bits, &mut inside_str);
+    let _ = on_block(set_bits, codepoint_cnt);
 
-    #[cfg(debug_assertions)]
+    #[cfg(feature = "trace")]
     println!(
         "\n📋\ntail_u8 len: {}\n{}",
         tail_u8.len(),
         ByteReport::_u8_as_str(tail_u8)
     );
+}
 
-    // 🎉 The index result!
-    #[cfg(debug_assertions)]
-    {
-        println!("🎉 index:\n{:?}", struct_acc);
-        println!("len: {:?}", struct_acc.len());
+/// The number of bytes sampled by `estimate_len`
+const SAMPLE_LEN: usize = 4096;
+
+/// An estimate of the length of the index: the count of delimiters and newlines in a sample of
+/// the head of the data, scaled to the length of the data.
+///
+/// 🔑 Sized up-front, the index does not grow (re-allocate and copy) 64 slots at a time.
+pub fn estimate_len(bytes: &[u8], dialect: &Dialect) -> usize {
+    let sample = &bytes[..bytes.len().min(SAMPLE_LEN)];
+    let cnt = sample
+        .iter()
+        .filter(|&&byte| {
+            byte == dialect.delimiter || byte == b'\n' || byte == b'\r'
+        })
+        .count();
+    // + 1 for the leading zero; + a margin for a sample that under-counts
+    let scaled = cnt * bytes.len() / sample.len().max(1);
+    scaled + scaled / 8 + 1
+}
+
+/// Compare an index with the one computed by the reference (scalar) reader. Reports the first
+/// key where the two differ, with the surrounding bytes as context.
+pub fn verify(
+    bytes: &[u8],
+    index: &StructureIndex,
+) -> Result<(), StructureError> {
+    verify_with(bytes, index, &Dialect::default())
+}
+
+/// `verify` with the reference reader configured for a dialect
+pub fn verify_with(
+    bytes: &[u8],
+    index: &StructureIndex,
+    dialect: &Dialect,
+) -> Result<(), StructureError> {
+    compare(bytes, index, &scalar::read_with(bytes, dialect))
+}
+
+/// Compare an index with a reference index of the same bytes
+pub(crate) fn compare(
+    bytes: &[u8],
+    index: &StructureIndex,
+    reference: &StructureIndex,
+) -> Result<(), StructureError> {
+    let found = index.iter();
+    let expected = reference.iter();
+
+    let first_diff = expected
+        .map(Some)
+        .chain(std::iter::repeat(None))
+        .zip(found.map(Some).chain(std::iter::repeat(None)))
+        .take(index.len().max(reference.len()))
+        .enumerate()
+        .find(|(_, (expected, found))| expected != found);
+
+    match first_diff {
+        None => Ok(()),
+        Some((key, (expected, found))) => {
+            // the context is centered on the first of the two offsets
+            let at = match (expected, found) {
+                (Some(e), Some(f)) => e.min(f),
+                (Some(pos), None) | (None, Some(pos)) => pos,
+                (None, None) => 0,
+            }
+            .min(bytes.len());
+            let context = &bytes[at.saturating_sub(CONTEXT_LEN)
+                ..(at + CONTEXT_LEN).min(bytes.len())];
+
+            Err(StructureError::IndexMismatch {
+                key,
+                expected,
+                found,
+                context: String::from_utf8_lossy(context).into_owned(),
+            })
+        }
     }
-    StructureIndex(cast_vec(struct_acc))
 }
 
+/// The number of bytes either side of a divergence reported by `verify`
+const CONTEXT_LEN: usize = 24;
+
 #[cfg(test)]
 mod tests {
     use crate::reader;
@@ -319,10 +449,10 @@ mod tests {
     fn mk_index() {
         let file = std::fs::File::open("./res/reader_test01.csv").unwrap();
         let memmap = unsafe { Mmap::map(&file).unwrap() };
-        let StructureIndex(index) = reader::read(&memmap);
+        let index = reader::read(&memmap);
         let cnt = index.len();
         println!("result: {:?}", index);
-        assert_eq!(4 as usize, *index[1], "The first structure pos: 4");
-        assert_eq!(95 as usize, *index[cnt - 1], "The last structure pos: 95");
+        assert_eq!(Some(4), index.get(1), "The first structure pos: 4");
+        assert_eq!(Some(95), index.get(cnt - 1), "The last structure pos: 95");
     }
 }
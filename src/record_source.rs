@@ -1,5 +1,9 @@
+use std::borrow::Cow;
 use std::fmt;
+use std::ops::Range;
 
+use crate::dialect::Dialect;
+use crate::encoding::Encoding;
 use crate::error::StructureError;
 use crate::stage1::{KeyToPos, NewLine, StructureIndex};
 
@@ -28,8 +32,12 @@ where
                     "len: {} records: {} problem: {}",
                     len, count, problem
                 )?;
-                writeln!(f, "first: {:?}", self.0.seek_record(0))?;
-                writeln!(f, "last:  {:?}", self.0.seek_record(last_record))?;
+                writeln!(f, "first: {:?}", self.0.seek_record_decoded(0))?;
+                writeln!(
+                    f,
+                    "last:  {:?}",
+                    self.0.seek_record_decoded(last_record)
+                )?;
                 Ok(())
             }
         }
@@ -65,12 +73,21 @@ impl<T> std::ops::DerefMut for WithRecordSource<T> {
 */
 // ------------------------------------------------------------------------------
 
+/// Random-access to the records and fields of a data source using its `StructureIndex`.
+///
+/// Three tiers of access:
+/// * `seek_*_bytes`: bounds-checked slices of the data source
+/// * `seek_*_str`: bounds-checked, and validated UTF-8
+/// * `seek_*_unchecked`: `unsafe`; no checks whatsoever
+///
+/// 🔑 A corrupt, or mismatched index returns an error; it cannot panic.
 pub trait RecordSource {
-    /// This is not the intended use of the Tape.  It is for debugging purposes only.
-    fn seek_record(
+    /// The location of a record in the data bytes; excludes the leading delimiter and the
+    /// terminator (same as `ByteRecord::span`).
+    fn record_span(
         &self,
         record_idx: u32,
-    ) -> Result<Option<&str>, StructureError> {
+    ) -> Result<Option<Range<usize>>, StructureError> {
         // The index has the memmap offset values
         // Which index value points to the start of the record?
         // record 0 = start of the memmap + header offset
@@ -79,36 +96,32 @@ pub trait RecordSource {
         {
             return Ok(None);
         };
-        let field_cnt = self.field_cnt();
-        let idx_start = (record_idx + 1) * (*self.record_jump_size()?) as u32;
+        let field_cnt = self.field_cnt() as usize;
+        let idx_start = (record_idx as usize + 1) * *self.record_jump_size()?;
 
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "trace")]
         {
             println!("Seek record: {}", record_idx);
             println!("field count: {}", &field_cnt);
             println!("row size: {:?}", &self.record_jump_size());
             println!("idx start: {}", &idx_start);
-            println!("idx end: {}", &idx_start + field_cnt);
+            println!("idx end: {}", idx_start + field_cnt);
         }
 
-        let mem_start = self.index()[idx_start as usize];
-        let mem_end = self.index()[idx_start as usize + field_cnt as usize];
-
-        Ok(Some(unsafe {
-            std::str::from_utf8_unchecked(
-                &self.data_bytes()[*mem_start + 1..*mem_end],
-            )
-        }))
+        span(
+            self.index(),
+            self.data_bytes(),
+            idx_start,
+            idx_start + field_cnt,
+        )
+        .map(Some)
     }
-    /// random-access
-    fn seek_field(
+    /// The location of a field in the data bytes; excludes the delimiters.
+    fn field_span(
         &self,
         record_idx: u32,
         field_idx: u32,
-    ) -> Result<Option<&str>, StructureError> {
-        // The index has the memmap offset values
-        // Which index value points to the start of the record?
-        // record 0 = start of the memmap + header offset
+    ) -> Result<Option<Range<usize>>, StructureError> {
         if record_idx + 1
             >= self.record_cnt().ok_or(StructureError::InvalidState)?
         {
@@ -117,25 +130,158 @@ pub trait RecordSource {
         if field_idx >= self.field_cnt() {
             return Ok(None);
         };
-        let field_cnt = self.field_cnt();
-        println!("Seek record: {}", record_idx);
-        println!("field count: {}", &field_cnt);
-        let row_size = match self.new_line_tag() {
-            NewLine::CRLF => field_cnt + 1,
-            _ => field_cnt,
-        };
-        println!("row size: {}", &row_size);
-        //
-        let idx_start = (record_idx + 1) * row_size + field_idx;
-        println!("idx start: {}", &idx_start);
-        println!("idx end: {}", &idx_start + 1);
-        let mem_start = self.index()[idx_start as usize];
-        let mem_end = self.index()[idx_start as usize + 1];
-
-        Ok(Some(unsafe {
-            std::str::from_utf8_unchecked(
-                &self.data_bytes()[(*mem_start) + 1..*mem_end],
-            )
+        let row_size = *self.record_jump_size()?;
+        let idx_start =
+            (record_idx as usize + 1) * row_size + field_idx as usize;
+
+        #[cfg(feature = "trace")]
+        {
+            println!("Seek field: {} {}", record_idx, field_idx);
+            println!("row size: {}", &row_size);
+            println!("idx start: {}", &idx_start);
+            println!("idx end: {}", idx_start + 1);
+        }
+
+        span(self.index(), self.data_bytes(), idx_start, idx_start + 1)
+            .map(Some)
+    }
+    /// The raw bytes of a record
+    fn seek_record_bytes(
+        &self,
+        record_idx: u32,
+    ) -> Result<Option<&[u8]>, StructureError> {
+        Ok(self
+            .record_span(record_idx)?
+            .map(|span| &self.data_bytes()[span]))
+    }
+    /// The raw bytes of a field
+    fn seek_field_bytes(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<&[u8]>, StructureError> {
+        Ok(self
+            .field_span(record_idx, field_idx)?
+            .map(|span| &self.data_bytes()[span]))
+    }
+    /// The record validated as UTF-8
+    fn seek_record_str(
+        &self,
+        record_idx: u32,
+    ) -> Result<Option<&str>, StructureError> {
+        match self.seek_record_bytes(record_idx)? {
+            None => Ok(None),
+            Some(bytes) => std::str::from_utf8(bytes).map(Some).map_err(|e| {
+                StructureError::InvalidUtf8 {
+                    record: record_idx,
+                    field: None,
+                    valid_up_to: e.valid_up_to(),
+                }
+            }),
+        }
+    }
+    /// The field validated as UTF-8
+    fn seek_field_str(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<&str>, StructureError> {
+        match self.seek_field_bytes(record_idx, field_idx)? {
+            None => Ok(None),
+            Some(bytes) => std::str::from_utf8(bytes).map(Some).map_err(|e| {
+                StructureError::InvalidUtf8 {
+                    record: record_idx,
+                    field: Some(field_idx),
+                    valid_up_to: e.valid_up_to(),
+                }
+            }),
+        }
+    }
+    /// This is not the intended use of the Tape.  It is for debugging purposes only.
+    /// Same as `seek_record_str`.
+    fn seek_record(
+        &self,
+        record_idx: u32,
+    ) -> Result<Option<&str>, StructureError> {
+        self.seek_record_str(record_idx)
+    }
+    /// random-access; same as `seek_field_str`.
+    fn seek_field(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<&str>, StructureError> {
+        self.seek_field_str(record_idx, field_idx)
+    }
+    /// The record without bounds or UTF-8 checks.
+    ///
+    /// # Safety
+    ///
+    /// The source must be initialized, `record_idx` must be less than `record_cnt - 1`, the
+    /// index must describe the data bytes, and the record must be valid UTF-8.
+    unsafe fn seek_record_unchecked(&self, record_idx: u32) -> &str {
+        let jump = *self.record_jump_size().unwrap_unchecked();
+        let idx_start = (record_idx as usize + 1) * jump;
+        let mem_start = self.index().get_unchecked(idx_start);
+        let mem_end = self
+            .index()
+            .get_unchecked(idx_start + self.field_cnt() as usize);
+
+        std::str::from_utf8_unchecked(
+            self.data_bytes().get_unchecked(mem_start + 1..mem_end),
+        )
+    }
+    /// The field without bounds or UTF-8 checks.
+    ///
+    /// # Safety
+    ///
+    /// The source must be initialized, `record_idx` must be less than `record_cnt - 1`,
+    /// `field_idx` less than `field_cnt`, the index must describe the data bytes, and the field
+    /// must be valid UTF-8.
+    unsafe fn seek_field_unchecked(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> &str {
+        let jump = *self.record_jump_size().unwrap_unchecked();
+        let idx_start = (record_idx as usize + 1) * jump + field_idx as usize;
+        let mem_start = self.index().get_unchecked(idx_start);
+        let mem_end = self.index().get_unchecked(idx_start + 1);
+
+        std::str::from_utf8_unchecked(
+            self.data_bytes().get_unchecked(mem_start + 1..mem_end),
+        )
+    }
+    /// The record transcoded from the source encoding to UTF-8. Borrows when no transcoding
+    /// is required.
+    fn seek_record_decoded(
+        &self,
+        record_idx: u32,
+    ) -> Result<Option<Cow<'_, str>>, StructureError> {
+        Ok(self
+            .seek_record_bytes(record_idx)?
+            .map(|bytes| self.encoding().decode(bytes)))
+    }
+    /// The field transcoded from the source encoding to UTF-8. Borrows when no transcoding is
+    /// required.
+    fn seek_field_decoded(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<Cow<'_, str>>, StructureError> {
+        Ok(self
+            .seek_field_bytes(record_idx, field_idx)?
+            .map(|bytes| self.encoding().decode(bytes)))
+    }
+    /// The field without the enclosing quotes, with the escapes resolved, and transcoded to
+    /// UTF-8. Borrows unless an escape or transcoding is required.
+    fn seek_field_value(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<Cow<'_, str>>, StructureError> {
+        Ok(self.seek_field_bytes(record_idx, field_idx)?.map(|bytes| {
+            self.encoding().decode_cow(self.dialect().unescape(bytes))
         }))
     }
     fn record_cnt(&self) -> Option<u32>;
@@ -144,6 +290,43 @@ pub trait RecordSource {
     fn field_cnt(&self) -> u32;
     fn new_line_tag(&self) -> &NewLine;
     fn data_bytes(&self) -> &[u8];
+    /// The encoding of the data bytes
+    fn encoding(&self) -> Encoding {
+        Encoding::Utf8
+    }
+    /// The quoting and escaping of the fields
+    fn dialect(&self) -> Dialect {
+        Dialect::default()
+    }
+}
+
+/// The bytes between two index keys: (index[start] + 1)..index[end]
+///
+/// Checks the keys against the index, and the resulting range against the data.
+fn span(
+    index: &StructureIndex,
+    data: &[u8],
+    key_start: usize,
+    key_end: usize,
+) -> Result<Range<usize>, StructureError> {
+    let out_of_bounds = |key| StructureError::IndexOutOfBounds {
+        key,
+        len: index.len(),
+    };
+    let start = index
+        .get(key_start)
+        .ok_or_else(|| out_of_bounds(key_start))?
+        + 1;
+    let end = index.get(key_end).ok_or_else(|| out_of_bounds(key_end))?;
+
+    if start > end || end > data.len() {
+        return Err(StructureError::InvalidSpan {
+            start,
+            end,
+            len: data.len(),
+        });
+    }
+    Ok(start..end)
 }
 /*
 impl fmt::Debug for dyn RecordSource {
@@ -159,3 +342,78 @@ impl fmt::Debug for dyn RecordSource {
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stage1::CodeUnitPos;
+
+    /// A source where the index and the data can be made to disagree
+    struct Source {
+        index: StructureIndex,
+        data: Vec<u8>,
+    }
+    impl RecordSource for Source {
+        fn record_cnt(&self) -> Option<u32> {
+            Some(3)
+        }
+        fn index(&self) -> &StructureIndex {
+            &self.index
+        }
+        fn record_jump_size(&self) -> Result<KeyToPos, StructureError> {
+            Ok(KeyToPos(2))
+        }
+        fn field_cnt(&self) -> u32 {
+            2
+        }
+        fn new_line_tag(&self) -> &NewLine {
+            &NewLine::LF
+        }
+        fn data_bytes(&self) -> &[u8] {
+            &self.data
+        }
+    }
+    fn source(index: Vec<usize>, data: &[u8]) -> Source {
+        Source {
+            index: StructureIndex::Usize(bytemuck::cast_vec::<
+                usize,
+                CodeUnitPos,
+            >(index)),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn checked_access() {
+        let src = source(vec![0, 1, 3, 5, 7, 9, 11], b"a,b\nc,d\ne,\xff\n");
+        assert_eq!(src.seek_field_bytes(0, 1).unwrap(), Some(&b"d"[..]));
+        assert_eq!(src.seek_record_str(0).unwrap(), Some("c,d"));
+        assert_eq!(src.seek_field_str(1, 0).unwrap(), Some("e"));
+        assert!(matches!(
+            src.seek_field_str(1, 1),
+            Err(StructureError::InvalidUtf8 {
+                record: 1,
+                field: Some(1),
+                valid_up_to: 0
+            })
+        ));
+        assert_eq!(src.seek_field_bytes(2, 0).unwrap(), None);
+        assert_eq!(src.seek_field_bytes(0, 2).unwrap(), None);
+        assert_eq!(unsafe { src.seek_field_unchecked(0, 0) }, "c");
+    }
+    #[test]
+    fn corrupt_index() {
+        // truncated index
+        let src = source(vec![0, 1, 3, 5], b"a,b\nc,d\n");
+        assert!(matches!(
+            src.seek_record_bytes(1),
+            Err(StructureError::IndexOutOfBounds { key: 4, len: 4 })
+        ));
+        // index points past the data
+        let src = source(vec![0, 1, 3, 5, 70, 9, 11], b"a,b\nc,d\n");
+        assert!(matches!(
+            src.seek_field_bytes(0, 1),
+            Err(StructureError::InvalidSpan { .. })
+        ));
+    }
+}
@@ -0,0 +1,464 @@
+///
+/// Record iteration
+///
+/// The records are read by stepping through the `StructureIndex` one record (`jump`) at a time.
+/// No allocation; a `Record` is a view of the index slots that describe it:
+///
+///   slots: [leading delimiter, field delimiters..., terminator]
+///          (field_cnt + 1 values)
+///
+/// field n = (slots[n] + 1)..slots[n + 1]
+///
+use std::borrow::Cow;
+use std::iter::FusedIterator;
+use std::ops::{Index, Range};
+
+use crate::dialect::Dialect;
+use crate::encoding::Encoding;
+use crate::error::StructureError;
+use crate::nulls::NullValues;
+use crate::number_format::NumberFormat;
+use crate::stage1::Slots;
+use crate::tape::Tape;
+use crate::temporal::{Date, DateFormat, DateTime, YearMonth};
+use crate::typed::{parse_field_with, FromField};
+
+/// Iterator over the records of a Tape (excludes the header).
+#[derive(Debug, Clone)]
+pub struct Records<'tape> {
+    bytes: &'tape [u8],
+    index: Slots<'tape>,
+    jump: usize,
+    field_cnt: usize,
+    encoding: Encoding,
+    dialect: Dialect,
+    number_format: NumberFormat,
+    nulls: &'tape NullValues,
+    front: u32,
+    back: u32,
+}
+
+impl<'tape> Records<'tape> {
+    pub(crate) fn new(tape: &'tape Tape) -> Self {
+        Records {
+            bytes: tape.bytes(),
+            index: tape.index().slots(),
+            jump: *tape.record_jump_size,
+            field_cnt: tape.header.field_cnt as usize,
+            encoding: tape.header.encoding(),
+            dialect: tape.header.dialect(),
+            number_format: tape.header.number_format(),
+            nulls: tape.header.null_values(),
+            front: 0,
+            back: tape.record_cnt.saturating_sub(1),
+        }
+    }
+    /// record_idx -> Record
+    fn record(&self, record_idx: u32) -> Record<'tape> {
+        // + 1: skip the header
+        let base = (record_idx as usize + 1) * self.jump;
+        Record {
+            bytes: self.bytes,
+            slots: self.index.range(base..base + self.field_cnt + 1),
+            encoding: self.encoding,
+            dialect: self.dialect,
+            number_format: self.number_format,
+            nulls: self.nulls,
+            idx: record_idx,
+        }
+    }
+}
+
+impl<'tape> Iterator for Records<'tape> {
+    type Item = Record<'tape>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let record = self.record(self.front);
+        self.front += 1;
+        Some(record)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front) as usize;
+        (len, Some(len))
+    }
+    /// random-access; no need to step through the skipped records
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.len() {
+            self.front = self.back;
+            return None;
+        }
+        self.front += n as u32;
+        self.next()
+    }
+}
+impl<'tape> DoubleEndedIterator for Records<'tape> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.record(self.back))
+    }
+}
+impl<'tape> ExactSizeIterator for Records<'tape> {}
+impl<'tape> FusedIterator for Records<'tape> {}
+
+/// A record of a Tape; a view of the index slots that describe the record.
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'tape> {
+    bytes: &'tape [u8],
+    slots: Slots<'tape>,
+    encoding: Encoding,
+    dialect: Dialect,
+    number_format: NumberFormat,
+    nulls: &'tape NullValues,
+    idx: u32,
+}
+
+impl<'tape> Record<'tape> {
+    /// The position of the record in the Tape (excludes the header)
+    pub fn idx(&self) -> u32 {
+        self.idx
+    }
+    /// The number of fields
+    pub fn len(&self) -> usize {
+        self.slots.len() - 1
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The raw bytes of a field
+    pub fn get(&self, field_idx: usize) -> Option<&'tape [u8]> {
+        let start = self.slots.get(field_idx)? + 1;
+        let end = self.slots.get(field_idx + 1)?;
+        self.bytes.get(start..end)
+    }
+    /// The field validated as UTF-8
+    pub fn get_str(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<&'tape str>, StructureError> {
+        match self.get(field_idx) {
+            None => Ok(None),
+            Some(bytes) => std::str::from_utf8(bytes).map(Some).map_err(|e| {
+                StructureError::InvalidUtf8 {
+                    record: self.idx,
+                    field: Some(field_idx as u32),
+                    valid_up_to: e.valid_up_to(),
+                }
+            }),
+        }
+    }
+    /// The field transcoded from the source encoding to UTF-8
+    pub fn get_decoded(&self, field_idx: usize) -> Option<Cow<'tape, str>> {
+        self.get(field_idx).map(|bytes| self.encoding.decode(bytes))
+    }
+    /// The field without the enclosing quotes, with the escapes resolved, and transcoded to
+    /// UTF-8. Borrows unless an escape or transcoding is required.
+    /// ⚠️ A null token is returned as is; `get_value` is the null-aware variant.
+    pub fn value(&self, field_idx: usize) -> Option<Cow<'tape, str>> {
+        self.get(field_idx)
+            .map(|bytes| self.encoding.decode_cow(self.dialect.unescape(bytes)))
+    }
+    /// The value of the field (see `value`); None when the field is null or missing.
+    pub fn get_value(&self, field_idx: usize) -> Option<Cow<'tape, str>> {
+        match self.is_null(field_idx) {
+            true => None,
+            false => self.value(field_idx),
+        }
+    }
+    /// Is the field null (see `NullValues`); a missing field is not null.
+    pub fn is_null(&self, field_idx: usize) -> bool {
+        self.get(field_idx)
+            .is_some_and(|raw| self.nulls.is_null(raw, &self.dialect))
+    }
+    /// The value of the field parsed to a type; None when null or missing.
+    pub fn get_typed<T: FromField>(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<T>, StructureError> {
+        let format = self.number_format;
+        self.get_with(field_idx, T::KIND, |value| {
+            T::from_formatted(value, &format)
+        })
+    }
+    /// raw -> value with a custom parser; the error hosts the position in the Tape
+    fn get_with<T>(
+        &self,
+        field_idx: usize,
+        kind: &'static str,
+        parse: impl FnOnce(&[u8]) -> Option<T>,
+    ) -> Result<Option<T>, StructureError> {
+        match self.get(field_idx) {
+            None => Ok(None),
+            Some(raw) if self.nulls.is_null(raw, &self.dialect) => Ok(None),
+            Some(raw) => parse_field_with(
+                raw,
+                (&self.dialect, self.encoding),
+                (self.idx, field_idx as u32),
+                kind,
+                parse,
+            ),
+        }
+    }
+    pub fn get_i64(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<i64>, StructureError> {
+        self.get_typed(field_idx)
+    }
+    pub fn get_u64(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<u64>, StructureError> {
+        self.get_typed(field_idx)
+    }
+    pub fn get_f64(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<f64>, StructureError> {
+        self.get_typed(field_idx)
+    }
+    pub fn get_bool(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<bool>, StructureError> {
+        self.get_typed(field_idx)
+    }
+    /// ISO (2016-09-30) or US (09/30/2016)
+    pub fn get_date(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<Date>, StructureError> {
+        self.get_typed(field_idx)
+    }
+    /// 2016-09
+    pub fn get_year_month(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<YearMonth>, StructureError> {
+        self.get_typed(field_idx)
+    }
+    /// ISO timestamp
+    pub fn get_datetime(
+        &self,
+        field_idx: usize,
+    ) -> Result<Option<DateTime>, StructureError> {
+        self.get_typed(field_idx)
+    }
+    pub fn get_date_with(
+        &self,
+        field_idx: usize,
+        format: &DateFormat,
+    ) -> Result<Option<Date>, StructureError> {
+        self.get_with(field_idx, Date::KIND, |value| format.parse_date(value))
+    }
+    pub fn get_datetime_with(
+        &self,
+        field_idx: usize,
+        format: &DateFormat,
+    ) -> Result<Option<DateTime>, StructureError> {
+        self.get_with(field_idx, DateTime::KIND, |value| {
+            format.parse_datetime(value)
+        })
+    }
+    /// The record as byte offsets into the data
+    pub fn as_byte_record(&self) -> ByteRecord<'tape> {
+        ByteRecord {
+            bytes: self.bytes,
+            slots: self.slots,
+            idx: self.idx,
+        }
+    }
+    /// Iterate over the raw bytes of the fields
+    pub fn iter(&self) -> Fields<'tape> {
+        Fields {
+            record: *self,
+            front: 0,
+            back: self.len(),
+        }
+    }
+}
+
+impl<'tape> Index<usize> for Record<'tape> {
+    type Output = [u8];
+
+    /// Panics when the field does not exist
+    fn index(&self, field_idx: usize) -> &Self::Output {
+        match self.get(field_idx) {
+            Some(bytes) => bytes,
+            None => panic!(
+                "field {} out of range for record {} with {} fields",
+                field_idx,
+                self.idx,
+                self.len()
+            ),
+        }
+    }
+}
+
+impl<'tape> IntoIterator for Record<'tape> {
+    type Item = &'tape [u8];
+    type IntoIter = Fields<'tape>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the fields of a record
+#[derive(Debug, Clone)]
+pub struct Fields<'tape> {
+    record: Record<'tape>,
+    front: usize,
+    back: usize,
+}
+
+impl<'tape> Iterator for Fields<'tape> {
+    type Item = &'tape [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.front += 1;
+        self.record.get(self.front - 1)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+impl<'tape> DoubleEndedIterator for Fields<'tape> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.record.get(self.back)
+    }
+}
+impl<'tape> ExactSizeIterator for Fields<'tape> {}
+impl<'tape> FusedIterator for Fields<'tape> {}
+
+/// Zero-copy view of a record: its location in the data and the index slots that describe it.
+///
+/// A span is a range of offsets into the data bytes (e.g., the memmap); it excludes the
+/// delimiters and the terminator.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteRecord<'tape> {
+    bytes: &'tape [u8],
+    slots: Slots<'tape>,
+    idx: u32,
+}
+
+/// The offset of a slot of a record; the slots of a record are in range
+fn pos(slots: Slots, key: usize) -> usize {
+    slots.get(key).expect("a slot of the record")
+}
+
+impl<'tape> ByteRecord<'tape> {
+    /// The position of the record in the Tape (excludes the header)
+    pub fn idx(&self) -> u32 {
+        self.idx
+    }
+    /// The number of fields
+    pub fn len(&self) -> usize {
+        self.slots.len() - 1
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The `StructureIndex` entries: leading delimiter, field delimiters, terminator
+    pub fn slots(&self) -> Slots<'tape> {
+        self.slots
+    }
+    /// The location of the record; excludes the leading delimiter and the terminator.
+    pub fn span(&self) -> Range<usize> {
+        pos(self.slots, 0) + 1..pos(self.slots, self.len())
+    }
+    /// The raw bytes of the record
+    pub fn as_bytes(&self) -> &'tape [u8] {
+        &self.bytes[self.span()]
+    }
+    /// The location of a field
+    pub fn field_span(&self, field_idx: usize) -> Option<Range<usize>> {
+        let start = self.slots.get(field_idx)? + 1;
+        let end = self.slots.get(field_idx + 1)?;
+        Some(start..end)
+    }
+    /// The raw bytes of a field
+    pub fn get(&self, field_idx: usize) -> Option<&'tape [u8]> {
+        self.bytes.get(self.field_span(field_idx)?)
+    }
+    /// Iterate over the locations of the fields
+    pub fn spans(&self) -> impl ExactSizeIterator<Item = Range<usize>> + 'tape {
+        let slots = self.slots;
+        (0..self.len()).map(move |n| pos(slots, n) + 1..pos(slots, n + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{create, Encoding, RecordSource};
+
+    #[test]
+    fn records() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let records = tape.as_records();
+        assert_eq!(records.len(), 7);
+
+        for (record, expected) in tape.as_records().zip(0..) {
+            assert_eq!(record.idx(), expected);
+            assert_eq!(record.len(), 8);
+            let joined = record.iter().collect::<Vec<_>>().join(&b","[..]);
+            assert_eq!(
+                Some(&joined[..]),
+                (&tape).seek_record_bytes(expected).unwrap()
+            );
+        }
+
+        let last = tape.as_records().next_back().unwrap();
+        assert_eq!(&last[5], &b"\"CASH,IT\""[..]);
+        assert_eq!(last.iter().next_back(), Some(&b"1"[..]));
+        assert_eq!(last.get(8), None);
+
+        let mut records = tape.as_records();
+        let third = records.nth(2).unwrap();
+        assert_eq!(third.get_str(0).unwrap(), Some("1003002815"));
+        assert_eq!(records.len(), 4);
+        assert_eq!(records.nth(10).map(|record| record.idx()), None);
+
+        let second = tape.as_records().nth(1).unwrap();
+        assert_eq!(
+            second.value(2).unwrap(),
+            "INTERNAL MED, CARD. ELECTROPHYSIOLOGY"
+        );
+    }
+    #[test]
+    fn byte_record() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let bytes = tape.bytes();
+        for record in tape.as_records() {
+            let byte_record = record.as_byte_record();
+            let span = byte_record.span();
+            assert_eq!(bytes[span.start - 1], b'\n');
+            assert_eq!(bytes[span.end], b'\r');
+            assert_eq!(
+                Some(byte_record.as_bytes()),
+                (&tape).seek_record_bytes(record.idx()).unwrap()
+            );
+            assert_eq!(byte_record.slots().len(), 9);
+            for (field_idx, field_span) in byte_record.spans().enumerate() {
+                assert_eq!(&bytes[field_span], &record[field_idx]);
+            }
+        }
+        let last = tape.as_records().next_back().unwrap().as_byte_record();
+        let span = last.field_span(5).unwrap();
+        assert_eq!(&bytes[span.start..span.end], &b"\"CASH,IT\""[..]);
+        assert_eq!(last.field_span(8), None);
+    }
+}
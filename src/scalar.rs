@@ -0,0 +1,183 @@
+///
+/// Reference reader
+///
+/// A byte-at-a-time state machine that builds the same `StructureIndex` as the vectorized
+/// Stage1.  It is the "truth" used to verify the SIMD output (`reader::verify`), and the fallback
+/// where the SIMD instructions are not available.
+///
+/// The rules replicated from Stage1:
+///  * the index starts with a zero
+///  * every comma, CR and LF outside of quotes is structure
+///  * a quote toggles the inside-a-string state (an escaped `""` toggles twice)
+///
+use std::ops::ControlFlow;
+
+use bytemuck::allocation::cast_vec;
+
+use crate::dialect::{Dialect, Escape};
+use crate::reader;
+use crate::stage1::StructureIndex;
+
+/// bytes -> StructureIndex
+pub fn read(bytes: &[u8]) -> StructureIndex {
+    read_with(bytes, &Dialect::default())
+}
+
+/// Read with the delimiter, quote and escape of a dialect.
+///
+/// A backslash-style escape hides the byte that follows from the state machine.  Stage1 has no
+/// equivalent; a dialect with such an escape is read here only.
+pub fn read_with(bytes: &[u8], dialect: &Dialect) -> StructureIndex {
+    read_into(bytes, dialect, Vec::new())
+}
+
+/// Read into a recycled accumulator (see `reader::read_into`)
+pub(crate) fn read_into(
+    bytes: &[u8],
+    dialect: &Dialect,
+    mut acc: Vec<usize>,
+) -> StructureIndex {
+    acc.clear();
+    acc.reserve(reader::estimate_len(bytes, dialect));
+    acc.push(0);
+    let mut in_string = false;
+    let escape = match dialect.escape {
+        Escape::Backslash(escape) => Some(escape),
+        _ => None,
+    };
+
+    let mut bytes = bytes.iter().enumerate();
+    while let Some((pos, &byte)) = bytes.next() {
+        match byte {
+            b if Some(b) == escape => {
+                bytes.next();
+            }
+            b if b == dialect.quote => in_string = !in_string,
+            b'\r' | b'\n' if !in_string => acc.push(pos),
+            b if b == dialect.delimiter && !in_string => acc.push(pos),
+            _ => (),
+        }
+    }
+    StructureIndex::Usize(cast_vec(acc))
+}
+
+/// The structure bitmaps of the blocks of 64 bytes; the equivalent of `reader::for_each_block`
+/// for a dialect the vectorized reader does not support.
+pub(crate) fn for_each_block(
+    bytes: &[u8],
+    dialect: &Dialect,
+    mut on_block: impl FnMut(u64, usize) -> ControlFlow<()>,
+) {
+    let mut in_string = false;
+    let mut escaped = false;
+    let escape = match dialect.escape {
+        Escape::Backslash(escape) => Some(escape),
+        _ => None,
+    };
+
+    for (block_idx, block) in bytes.chunks(64).enumerate() {
+        let mut set_bits = 0_u64;
+        for (bit, &byte) in block.iter().enumerate() {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+            match byte {
+                b if Some(b) == escape => escaped = true,
+                b if b == dialect.quote => in_string = !in_string,
+                b'\r' | b'\n' if !in_string => set_bits |= 1 << bit,
+                b if b == dialect.delimiter && !in_string => {
+                    set_bits |= 1 << bit
+                }
+                _ => (),
+            }
+        }
+        if on_block(set_bits, block_idx * 64).is_break() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift; reproducible inputs without a dependency
+    struct Rng(u64);
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    /// Random csv-like input; quotes and CRLF are pushed onto the 64-byte edges.
+    fn input(rng: &mut Rng) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"abc ,,,\"\"\r\n\n";
+        let len = rng.below(400);
+        let mut bytes = (0..len)
+            .map(|_| ALPHABET[rng.below(ALPHABET.len())])
+            .collect::<Vec<u8>>();
+        for edge in (63..len).step_by(64) {
+            match rng.below(4) {
+                0 => bytes[edge] = b'"',
+                1 if edge + 1 < len => {
+                    bytes[edge] = b'\r';
+                    bytes[edge + 1] = b'\n';
+                }
+                2 if edge + 1 < len => {
+                    bytes[edge] = b'"';
+                    bytes[edge + 1] = b'"';
+                }
+                _ => (),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn simple() {
+        let index = read(b"a,\"b,c\"\r\nd,e\n");
+        let index = index.iter().collect::<Vec<_>>();
+        assert_eq!(index, vec![0, 1, 7, 8, 10, 12]);
+    }
+    #[test]
+    fn differential() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..300 {
+            let bytes = input(&mut rng);
+            // shift the start to exercise the unaligned head
+            let mut host = vec![b'x'; 16];
+            host.extend_from_slice(&bytes);
+            let offset = 16 - rng.below(16);
+            let shifted = &host[offset..];
+
+            let index = reader::read(shifted);
+            if let Err(e) = reader::verify(shifted, &index) {
+                panic!("{} input: {:?}", e, String::from_utf8_lossy(shifted));
+            }
+        }
+    }
+    #[test]
+    fn verify_reports_divergence() {
+        let bytes = b"a,b\nc,d\n";
+        let mut index = read(bytes);
+        if let StructureIndex::Usize(positions) = &mut index {
+            *positions[2] = 2;
+        }
+        assert!(matches!(
+            reader::verify(bytes, &index),
+            Err(crate::StructureError::IndexMismatch {
+                key: 2,
+                expected: Some(3),
+                found: Some(2),
+                ..
+            })
+        ));
+    }
+}
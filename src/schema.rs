@@ -0,0 +1,594 @@
+///
+/// Column type inference
+///
+/// Each column is scanned (all of the records, or a stratified sample) and the values are tried
+/// as each of the typed candidates:
+///
+///   Integer -> Float -> Boolean -> Date -> YearMonth -> DateTime
+///
+/// The candidate that parses the most values wins; a tie goes to the more specific type (e.g.
+/// `0`/`1` is an Integer).  When no candidate reaches `MIN_CONFIDENCE`, the column is
+/// Categorical (few distinct values that repeat) or Text.
+///
+/// ⚠️  A number with a leading zero (`02134`) is not a number: zip codes and the like keep the
+///    zero as text.  `NaN` and `inf` are floats only with `NumberFormat::non_finite`.
+///
+/// 🔑 The `Schema` is serializable; a loader can consume it in place of a list of columns.
+///
+/// A Schema can also be declared, as JSON or as text, with constraints on the values (see
+/// `Tape::validate`):
+///
+///   # name: type [nullable]; then the constraints, indented
+///   NPI Number: integer
+///       pattern: 10 digits
+///   Practitioner State: categorical nullable
+///       values: FL | NY
+///   NRx Count: integer
+///       min: 0
+///       max: 100
+///
+/// The statistics of a declared column are zero.
+///
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::column::Column;
+use crate::error::StructureError;
+use crate::number_format::NumberFormat;
+use crate::pattern::Pattern;
+use crate::tape::Tape;
+use crate::temporal::{Date, DateTime, YearMonth};
+use crate::typed::FromField;
+
+/// The share of the values that must parse to a typed candidate
+pub const MIN_CONFIDENCE: f64 = 0.95;
+/// The number of record numbers reported that do not parse to the type
+pub const MAX_COUNTEREXAMPLES: usize = 5;
+/// The most distinct values of a Categorical column
+pub const MAX_CATEGORIES: usize = 32;
+
+/// Which records are scanned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleSpec {
+    #[default]
+    All,
+    /// The records are split into `chunks` ranges; the first `records_per_chunk` of each range
+    /// are scanned.
+    Stratified { chunks: u32, records_per_chunk: u32 },
+}
+
+/// The inferred type of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    Integer,
+    Float,
+    Boolean,
+    /// 2016-09-30 or 09/30/2016
+    Date,
+    /// 2016-09
+    YearMonth,
+    /// 2016-09-30T13:45:00
+    DateTime,
+    Categorical,
+    Text,
+}
+
+/// What was learned of a column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub column_type: ColumnType,
+    /// At least one value is null (empty, or a null token; see `NullValues`)
+    #[serde(default)]
+    pub nullable: bool,
+    #[serde(default)]
+    pub null_cnt: u32,
+    /// The length of the values in characters, untrimmed (excludes the null values)
+    #[serde(default)]
+    pub min_len: u32,
+    #[serde(default)]
+    pub max_len: u32,
+    /// The share of the non-null values that parse to the type
+    #[serde(default)]
+    pub confidence: f64,
+    /// Records (excludes the header) with a value that does not parse to the type
+    #[serde(default)]
+    pub counterexamples: Vec<u32>,
+    /// The allowed values; declared only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<Pattern>,
+    /// Compared as the type of the column (text: in the order of the bytes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Bound>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Bound>,
+}
+
+/// A limit of the values: `0`, `2.5`, `"2015-01"`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Bound {
+    Number(f64),
+    Text(String),
+}
+
+impl Bound {
+    fn parse(text: &str) -> Bound {
+        match text.parse() {
+            Ok(number) => Bound::Number(number),
+            Err(_) => Bound::Text(text.to_string()),
+        }
+    }
+}
+
+impl ColumnSchema {
+    /// A declared column; no constraints
+    pub fn new(name: &str, column_type: ColumnType, nullable: bool) -> Self {
+        ColumnSchema {
+            name: name.to_string(),
+            column_type,
+            nullable,
+            null_cnt: 0,
+            min_len: 0,
+            max_len: 0,
+            confidence: 0.0,
+            counterexamples: Vec::new(),
+            values: None,
+            pattern: None,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl ColumnType {
+    /// The name in the JSON and text forms
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Date => "date",
+            ColumnType::YearMonth => "yearmonth",
+            ColumnType::DateTime => "datetime",
+            ColumnType::Categorical => "categorical",
+            ColumnType::Text => "text",
+        }
+    }
+    fn from_name(name: &str) -> Option<ColumnType> {
+        TYPED
+            .iter()
+            .chain(&[ColumnType::Categorical, ColumnType::Text])
+            .find(|column_type| column_type.name() == name)
+            .copied()
+    }
+}
+
+/// The columns of a Tape
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub columns: Vec<ColumnSchema>,
+    /// The number of records scanned
+    #[serde(default)]
+    pub sampled: u32,
+}
+
+impl Schema {
+    pub fn get(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+    pub fn from_json(json: &str) -> Result<Schema, StructureError> {
+        serde_json::from_str(json).map_err(|e| StructureError::InvalidSchema {
+            line: e.line(),
+            reason: e.to_string(),
+        })
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a serializable schema")
+    }
+    /// The text form (see the module)
+    pub fn from_text(text: &str) -> Result<Schema, StructureError> {
+        let mut columns: Vec<ColumnSchema> = Vec::new();
+        for (line_idx, line) in text.lines().enumerate() {
+            let invalid = |reason: String| StructureError::InvalidSchema {
+                line: line_idx + 1,
+                reason,
+            };
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            // ⚠️  a name may have a ':'; a constraint value may too
+            let constraint = line.starts_with(char::is_whitespace);
+            let (key, value) = match constraint {
+                true => trimmed.split_once(':'),
+                false => trimmed.rsplit_once(':'),
+            }
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| invalid(format!("no ':' in {:?}", trimmed)))?;
+            // a constraint of the last column
+            if constraint {
+                let column = columns.last_mut().ok_or_else(|| {
+                    invalid("a constraint before the first column".to_string())
+                })?;
+                match key {
+                    "values" => {
+                        let values =
+                            value.split('|').map(|v| v.trim().to_string());
+                        column.values = Some(values.collect());
+                    }
+                    "pattern" => {
+                        let pattern = Pattern::new(value)
+                            .map_err(|e| invalid(e.to_string()))?;
+                        column.pattern = Some(pattern);
+                    }
+                    "min" => column.min = Some(Bound::parse(value)),
+                    "max" => column.max = Some(Bound::parse(value)),
+                    _ => {
+                        return Err(invalid(format!(
+                            "unknown constraint {:?}",
+                            key
+                        )))
+                    }
+                }
+                continue;
+            }
+            let mut words = value.split_whitespace();
+            let column_type =
+                words.next().and_then(ColumnType::from_name).ok_or_else(
+                    || invalid(format!("unknown type in {:?}", value)),
+                )?;
+            let nullable = match words.next() {
+                None => false,
+                Some("nullable") => true,
+                Some(word) => {
+                    return Err(invalid(format!("unknown flag {:?}", word)))
+                }
+            };
+            columns.push(ColumnSchema::new(key, column_type, nullable));
+        }
+        Ok(Schema {
+            columns,
+            sampled: 0,
+        })
+    }
+}
+
+/// Infer the Schema of the Tape
+pub(crate) fn infer(tape: &Tape, sample: SampleSpec) -> Schema {
+    let number_format = tape.header.number_format();
+    let mut sampled = 0;
+    let columns = tape
+        .header()
+        .iter()
+        .enumerate()
+        .map(|(field_idx, name)| {
+            let column = tape.column(field_idx).expect("field in the header");
+            let mut stats = Stats::default();
+            for range in ranges(&column, sample) {
+                stats.scan(&range, &number_format);
+            }
+            sampled = stats.sampled;
+            stats.into_schema(name.clone())
+        })
+        .collect();
+    Schema { columns, sampled }
+}
+
+/// The views of the column to scan
+fn ranges<'tape>(
+    column: &Column<'tape>,
+    sample: SampleSpec,
+) -> Vec<Column<'tape>> {
+    let len = column.len() as u32;
+    match sample {
+        SampleSpec::Stratified {
+            chunks,
+            records_per_chunk,
+        } if chunks > 0
+            && (chunks as u64 * records_per_chunk as u64) < len as u64 =>
+        {
+            (0..chunks as u64)
+                .filter_map(|chunk| {
+                    let start = (chunk * len as u64 / chunks as u64) as u32;
+                    let end = ((chunk + 1) * len as u64 / chunks as u64) as u32;
+                    column.slice(start..end.min(start + records_per_chunk))
+                })
+                .collect()
+        }
+        _ => vec![*column],
+    }
+}
+
+/// A typed candidate of a column
+#[derive(Debug, Default)]
+struct Candidate {
+    parsed: u32,
+    counterexamples: Vec<u32>,
+}
+
+impl Candidate {
+    fn count(&mut self, parsed: bool, record: u32) {
+        if parsed {
+            self.parsed += 1;
+        } else if self.counterexamples.len() < MAX_COUNTEREXAMPLES {
+            self.counterexamples.push(record);
+        }
+    }
+}
+
+/// The tally of a column
+#[derive(Debug, Default)]
+struct Stats {
+    sampled: u32,
+    null_cnt: u32,
+    min_len: Option<u32>,
+    max_len: u32,
+    /// in the order of TYPED
+    candidates: [Candidate; 6],
+    /// ⚠️  capped at MAX_CATEGORIES + 1
+    distinct: HashSet<String>,
+}
+
+const TYPED: [ColumnType; 6] = [
+    ColumnType::Integer,
+    ColumnType::Float,
+    ColumnType::Boolean,
+    ColumnType::Date,
+    ColumnType::YearMonth,
+    ColumnType::DateTime,
+];
+
+/// "02134", "-007"; a zero alone, or before the decimal separator, is a number
+fn leading_zero(value: &str) -> bool {
+    let digits = value.trim_start_matches(['+', '-', '(']).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+impl Stats {
+    fn scan(&mut self, column: &Column, number_format: &NumberFormat) {
+        let first = column.first_record();
+        // null: empty, or a null token of the Tape (see `NullValues`)
+        for (nth, value) in column.nullable_values().enumerate() {
+            let record = first + nth as u32;
+            self.sampled += 1;
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    self.null_cnt += 1;
+                    continue;
+                }
+            };
+            // ⚠️  untrimmed: the value as written (see `SqlDump`)
+            let len = value.chars().count() as u32;
+            self.min_len = Some(self.min_len.map_or(len, |min| min.min(len)));
+            self.max_len = self.max_len.max(len);
+
+            let value = value.trim();
+
+            let bytes = value.as_bytes();
+            // ⚠️  "02134" is an identifier (a zip code, an NPI); a number drops the zero
+            let number = !leading_zero(value);
+            let parsed = [
+                number && i64::from_formatted(bytes, number_format).is_some(),
+                number
+                    && f64::from_formatted(bytes, number_format).is_some_and(
+                        |float| float.is_finite() || number_format.non_finite,
+                    ),
+                bool::from_field(bytes).is_some(),
+                Date::from_field(bytes).is_some(),
+                YearMonth::from_field(bytes).is_some(),
+                DateTime::from_field(bytes).is_some(),
+            ];
+            for (candidate, parsed) in self.candidates.iter_mut().zip(parsed) {
+                candidate.count(parsed, record);
+            }
+            if self.distinct.len() <= MAX_CATEGORIES {
+                self.distinct.insert(value.to_string());
+            }
+        }
+    }
+    fn into_schema(mut self, name: String) -> ColumnSchema {
+        let non_null = self.sampled - self.null_cnt;
+        let confidence = |candidate: &Candidate| match non_null {
+            0 => 0.0,
+            _ => candidate.parsed as f64 / non_null as f64,
+        };
+        // the first of the best; the candidates are ordered most specific first
+        let best = (0..TYPED.len()).fold(0, |best, i| {
+            match confidence(&self.candidates[i])
+                > confidence(&self.candidates[best])
+            {
+                true => i,
+                false => best,
+            }
+        });
+        let best_confidence = confidence(&self.candidates[best]);
+        let (column_type, confidence, counterexamples) =
+            if best_confidence >= MIN_CONFIDENCE {
+                let counterexamples =
+                    std::mem::take(&mut self.candidates[best].counterexamples);
+                (TYPED[best], best_confidence, counterexamples)
+            } else if self.distinct.len() <= MAX_CATEGORIES
+                && self.distinct.len() * 2 <= non_null as usize
+            {
+                (ColumnType::Categorical, 1.0, Vec::new())
+            } else {
+                (ColumnType::Text, 1.0, Vec::new())
+            };
+        ColumnSchema {
+            name,
+            column_type,
+            nullable: self.null_cnt > 0,
+            null_cnt: self.null_cnt,
+            min_len: self.min_len.unwrap_or(0),
+            max_len: self.max_len,
+            confidence,
+            counterexamples,
+            values: None,
+            pattern: None,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create, Encoding, TapeBuilder};
+
+    #[test]
+    fn sample_rx() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        assert_eq!(schema.sampled, 7);
+        let types = schema
+            .columns
+            .iter()
+            .map(|column| column.column_type)
+            .collect::<Vec<_>>();
+        use ColumnType::*;
+        assert_eq!(
+            types,
+            vec![
+                Integer,
+                Text,
+                Text,
+                Categorical,
+                Integer,
+                Categorical,
+                YearMonth,
+                Integer
+            ]
+        );
+        let zip = schema.get("Practitioner Zip Code").unwrap();
+        assert_eq!((zip.min_len, zip.max_len), (1, 5));
+        assert!(!zip.nullable);
+
+        let json = serde_json::to_string(&schema).unwrap();
+        assert!(json.contains("\"column_type\":\"categorical\""));
+        let back: Schema = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, schema);
+    }
+    #[test]
+    fn declared() {
+        let text = "# the contract\n\
+                    NPI Number: integer\n\
+                    \tpattern: 10 digits\n\
+                    Practitioner State: categorical nullable\n\
+                    \tvalues: FL | NY\n\
+                    NRx Count: integer\n\
+                    \tmin: 0\n\
+                    \tmax: 100\n\
+                    Year-Month: yearmonth\n\
+                    \tmin: 2016-01\n";
+        let schema = Schema::from_text(text).unwrap();
+        assert_eq!(schema.columns.len(), 4);
+        let state = schema.get("Practitioner State").unwrap();
+        assert!(state.nullable);
+        assert_eq!(state.values, Some(vec!["FL".into(), "NY".into()]));
+        let nrx = schema.get("NRx Count").unwrap();
+        assert_eq!(nrx.max, Some(Bound::Number(100.0)));
+        let month = schema.get("Year-Month").unwrap();
+        assert_eq!(month.min, Some(Bound::Text("2016-01".into())));
+
+        let back = Schema::from_json(&schema.to_json()).unwrap();
+        assert_eq!(back, schema);
+        let json = r#"{"columns": [{"name": "id", "column_type": "number"}]}"#;
+        let schema = Schema::from_json(json);
+        assert!(matches!(schema, Err(StructureError::InvalidSchema { .. })));
+        let json = r#"{"columns": [{"name": "id", "column_type": "integer", "min": 1}]}"#;
+        let id = Schema::from_json(json).unwrap().columns.remove(0);
+        assert_eq!((id.nullable, id.min), (false, Some(Bound::Number(1.0))));
+
+        let times = Schema::from_text(
+            "Time: text\n    pattern: 99:99\n    values: 09:30 | 16:00\n",
+        )
+        .unwrap();
+        let time = times.get("Time").unwrap();
+        assert_eq!(time.pattern.as_ref().map(|p| p.as_str()), Some("99:99"));
+        assert_eq!(time.values, Some(vec!["09:30".into(), "16:00".into()]));
+        let ratio = Schema::from_text("a:b ratio: float\n").unwrap();
+        assert_eq!(ratio.columns[0].name, "a:b ratio");
+
+        let error = Schema::from_text("id: integer\n    length: 3\n");
+        assert!(matches!(
+            error,
+            Err(StructureError::InvalidSchema { line: 2, .. })
+        ));
+        assert!(Schema::from_text("id: number\n").is_err());
+    }
+    #[test]
+    fn null_tokens() {
+        let data = b"a,b\n1,x\nNA,y\n2,\\N\n";
+        let tape = TapeBuilder::new()
+            .null_values(crate::NullValues::common())
+            .build_from_bytes(&data[..])
+            .unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        let a = schema.get("a").unwrap();
+        assert_eq!(
+            (a.column_type, a.nullable, a.null_cnt),
+            (ColumnType::Integer, true, 1)
+        );
+        assert!(a.counterexamples.is_empty());
+        assert!(schema.get("b").unwrap().nullable);
+    }
+    #[test]
+    fn identifiers_and_non_finite() {
+        let data = b"zip,n,x,y\n02134,0,NaN,1.5\n10001,-7,inf,infinity\n";
+        let tape = TapeBuilder::new().build_from_bytes(&data[..]).unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        let types = schema
+            .columns
+            .iter()
+            .map(|column| column.column_type)
+            .collect::<Vec<_>>();
+        use ColumnType::*;
+        assert_eq!(types, vec![Text, Integer, Text, Text]);
+
+        let tape = TapeBuilder::new()
+            .number_format(NumberFormat::default().non_finite())
+            .build_from_bytes(&data[..])
+            .unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        assert_eq!(schema.get("x").unwrap().column_type, Float);
+        assert_eq!(schema.get("y").unwrap().column_type, Float);
+        assert_eq!(schema.get("zip").unwrap().column_type, Text);
+    }
+    #[test]
+    fn counterexamples_and_sample() {
+        let mut data = String::from("id,amount,flag\n");
+        for i in 0..100 {
+            let amount = match i {
+                42 => "n/a".to_string(),
+                _ => format!("{}.5", i),
+            };
+            let flag = if i % 10 == 0 { "" } else { "yes" };
+            data.push_str(&format!("{},{},{}\n", i, amount, flag));
+        }
+        let tape = TapeBuilder::new()
+            .build_from_bytes(data.into_bytes())
+            .unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        let amount = schema.get("amount").unwrap();
+        assert_eq!(amount.column_type, ColumnType::Float);
+        assert_eq!(amount.counterexamples, vec![42]);
+        assert!((amount.confidence - 0.99).abs() < 1e-9);
+        let flag = schema.get("flag").unwrap();
+        assert_eq!(flag.column_type, ColumnType::Boolean);
+        assert_eq!(flag.null_cnt, 10);
+
+        let sample = tape.infer_schema(SampleSpec::Stratified {
+            chunks: 4,
+            records_per_chunk: 5,
+        });
+        assert_eq!(sample.sampled, 20);
+        assert!(sample.get("amount").unwrap().counterexamples.is_empty());
+    }
+}
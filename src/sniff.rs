@@ -0,0 +1,300 @@
+///
+/// Dialect sniffing
+///
+/// A prefix of the data is read with each of the candidate dialects (delimiter x quote); the
+/// structure is found by the same Stage1 classifier that builds the Tape (see `visitor`).  The
+/// candidate with the most consistent number of fields per record wins:
+///
+///   score = (share of the records with the most frequent field count, field count)
+///
+/// 🔑 The first of the best; the candidates are ordered most likely first (',' '"').
+///
+/// Also reported: the BOM, Excel's `sep=;` first line, the newline, and whether the first
+/// record looks like a header (distinct names that do not parse as values, over typed
+/// columns or names that do not recur).
+///
+use std::collections::HashMap;
+
+use crate::builder::{HeaderMode, TapeBuilder};
+use crate::dialect::{Dialect, Escape};
+use crate::encoding::UTF8_BOM;
+use crate::error::StructureError;
+use crate::stage1::NewLine;
+use crate::temporal::{Date, DateTime, YearMonth};
+use crate::typed::FromField;
+use crate::visitor::{self, Visitor};
+
+/// The number of bytes read
+pub const PREFIX_LEN: usize = 64 * 1024;
+/// The number of records kept to describe the header
+const SAMPLE_RECORDS: usize = 64;
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+const QUOTES: [u8; 2] = [b'"', b'\''];
+
+/// What was found in the prefix
+#[derive(Debug, Clone, Copy)]
+pub struct Sniff {
+    pub dialect: Dialect,
+    pub new_line: NewLine,
+    /// A UTF-8 BOM
+    pub bom: bool,
+    /// Excel's `sep=;` first line (the delimiter is taken from the line)
+    pub sep_line: bool,
+    pub header: HeaderMode,
+    /// The most frequent number of fields
+    pub field_cnt: u32,
+    /// The share of the records with `field_cnt` fields
+    pub consistency: f64,
+}
+
+impl Sniff {
+    /// A builder configured with the findings
+    pub fn builder(&self) -> TapeBuilder {
+        TapeBuilder::new()
+            .dialect(self.dialect)
+            .header(self.header)
+            .skip_lines(self.sep_line as u32)
+    }
+}
+
+/// The dialect of the data
+pub fn sniff(bytes: &[u8]) -> Dialect {
+    sniff_details(bytes).dialect
+}
+
+/// The dialect, newline, BOM, `sep=` line and header of the data
+pub fn sniff_details(bytes: &[u8]) -> Sniff {
+    let bom = bytes.starts_with(UTF8_BOM);
+    let mut prefix = &bytes[bom as usize * UTF8_BOM.len()..];
+    let sep = sep_line(prefix);
+    if let Some((_, len)) = sep {
+        prefix = &prefix[len..];
+    }
+    // ⚠️  a truncated prefix ends with the last complete line
+    if prefix.len() > PREFIX_LEN {
+        let end = prefix[..PREFIX_LEN]
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(PREFIX_LEN, |end| end + 1);
+        prefix = &prefix[..end];
+    }
+    let escape = escape(prefix);
+    let delimiters = match sep {
+        Some((delimiter, _)) => vec![delimiter],
+        None => DELIMITERS.to_vec(),
+    };
+    let best = delimiters
+        .iter()
+        .flat_map(|&delimiter| {
+            QUOTES.iter().map(move |&quote| Dialect {
+                delimiter,
+                quote,
+                escape,
+                ..Dialect::default()
+            })
+        })
+        .map(|dialect| Tally::new(prefix, dialect))
+        .reduce(|best, tally| match tally.rank() > best.rank() {
+            true => tally,
+            false => best,
+        })
+        .expect("a candidate");
+
+    let (field_cnt, consistency) = best.score();
+    Sniff {
+        dialect: best.dialect,
+        new_line: new_line(prefix),
+        bom,
+        sep_line: sep.is_some(),
+        header: match best.has_header() {
+            true => HeaderMode::Present,
+            false => HeaderMode::Absent,
+        },
+        field_cnt,
+        consistency,
+    }
+}
+
+/// `sep=;` -> (delimiter, length of the line)
+fn sep_line(bytes: &[u8]) -> Option<(u8, usize)> {
+    let line = bytes.strip_prefix(b"sep=")?;
+    let delimiter = *line.first()?;
+    match &line[1..] {
+        [b'\r', b'\n', ..] => Some((delimiter, 7)),
+        [b'\n', ..] => Some((delimiter, 6)),
+        [] => Some((delimiter, 5)),
+        _ => None,
+    }
+}
+
+/// `\"` without `""` is a backslash escape
+fn escape(bytes: &[u8]) -> Escape {
+    let has = |pattern: &[u8]| bytes.windows(2).any(|pair| pair == pattern);
+    match has(b"\\\"") && !has(b"\"\"") {
+        true => Escape::Backslash(b'\\'),
+        false => Escape::Doubled,
+    }
+}
+
+/// The terminator of the first line
+fn new_line(bytes: &[u8]) -> NewLine {
+    match bytes.iter().position(|&byte| byte == b'\n') {
+        Some(pos) if pos > 0 && bytes[pos - 1] == b'\r' => NewLine::CRLF,
+        _ => NewLine::LF,
+    }
+}
+
+/// The field counts, and the first records, read with a candidate dialect
+struct Tally {
+    dialect: Dialect,
+    field_cnts: Vec<u32>,
+    /// of the current record
+    fields: u32,
+    records: Vec<Vec<Vec<u8>>>,
+}
+
+impl Tally {
+    fn new(bytes: &[u8], dialect: Dialect) -> Tally {
+        let mut tally = Tally {
+            dialect,
+            field_cnts: Vec::new(),
+            fields: 0,
+            records: Vec::new(),
+        };
+        // a failed read scores zero
+        let _ = visitor::visit(bytes, &dialect, &mut tally);
+        tally
+    }
+    /// (field count, consistency); a single field scores zero
+    fn score(&self) -> (u32, f64) {
+        let mut frequency = HashMap::new();
+        for &field_cnt in &self.field_cnts {
+            *frequency.entry(field_cnt).or_insert(0_u32) += 1;
+        }
+        match frequency
+            .into_iter()
+            .max_by_key(|&(field_cnt, cnt)| (cnt, field_cnt))
+        {
+            Some((field_cnt, cnt)) if field_cnt > 1 => {
+                (field_cnt, cnt as f64 / self.field_cnts.len() as f64)
+            }
+            _ => (1, 0.0),
+        }
+    }
+    /// (consistency, field count); see the score
+    fn rank(&self) -> (f64, u32) {
+        let (field_cnt, consistency) = self.score();
+        (consistency, field_cnt)
+    }
+    /// Distinct, non-empty names that are not values; and a typed column, or names that do
+    /// not recur in their column.
+    fn has_header(&self) -> bool {
+        let (first, rest) = match self.records.split_first() {
+            Some((first, rest)) if !rest.is_empty() => (first, rest),
+            _ => return true,
+        };
+        let mut names = first.iter().collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        if names.len() != first.len()
+            || first.iter().any(|name| name.is_empty() || is_value(name))
+        {
+            return false;
+        }
+        let typed_column = (0..first.len()).any(|field| {
+            let values = rest
+                .iter()
+                .filter_map(|record| record.get(field))
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>();
+            !values.is_empty() && values.iter().all(|value| is_value(value))
+        });
+        let recurs = first.iter().enumerate().any(|(field, name)| {
+            rest.iter().any(|record| record.get(field) == Some(name))
+        });
+        typed_column || !recurs
+    }
+}
+
+impl Visitor for Tally {
+    fn on_field(&mut self, record: u32, _field: u32, bytes: &[u8]) {
+        self.fields += 1;
+        if (record as usize) < SAMPLE_RECORDS {
+            if self.records.len() <= record as usize {
+                self.records.push(Vec::new());
+            }
+            let value = self.dialect.unescape(bytes);
+            self.records[record as usize].push(value.trim_ascii().to_vec());
+        }
+    }
+    fn on_record_end(&mut self, _record: u32) {
+        self.field_cnts.push(self.fields);
+        self.fields = 0;
+    }
+    /// The field counts are compared; a ragged record is not an error here
+    fn on_error(
+        &mut self,
+        _error: StructureError,
+    ) -> Result<(), StructureError> {
+        Ok(())
+    }
+}
+
+/// A number or a date
+fn is_value(value: &[u8]) -> bool {
+    f64::from_field(value).is_some()
+        || Date::from_field(value).is_some()
+        || YearMonth::from_field(value).is_some()
+        || DateTime::from_field(value).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RecordSource;
+
+    #[test]
+    fn delimiters() {
+        let semicolon =
+            b"name;amount;city\r\nOslo;\"1,5\";Oslo\r\nBergen;2,25;x\r\n";
+        let found = sniff_details(semicolon);
+        assert_eq!(found.dialect.delimiter, b';');
+        assert_eq!(found.field_cnt, 3);
+        assert!(matches!(found.new_line, NewLine::CRLF));
+        assert_eq!(found.header, HeaderMode::Present);
+
+        let tabs = b"1\t2\t3\n4\t5\t6\n";
+        let found = sniff_details(tabs);
+        assert_eq!(found.dialect.delimiter, b'\t');
+        assert_eq!(found.header, HeaderMode::Absent);
+
+        let pipes = b"a|'b|c'\n1|'x|y'\n2|'z'\n";
+        let dialect = sniff(pipes);
+        assert_eq!((dialect.delimiter, dialect.quote), (b'|', b'\''));
+
+        let rx = std::fs::read("./res/sample_rx.csv").unwrap();
+        let found = sniff_details(&rx);
+        assert_eq!(found.dialect.delimiter, b',');
+        assert_eq!(found.dialect.quote, b'"');
+        assert!(found.bom);
+        assert_eq!(found.field_cnt, 8);
+        assert_eq!(found.consistency, 1.0);
+        assert_eq!(found.header, HeaderMode::Present);
+    }
+    #[test]
+    fn excel_sep_line() {
+        let data = b"sep=|\nid|name\n1|a,b\n2|c\n";
+        let found = sniff_details(data);
+        assert!(found.sep_line);
+        assert_eq!(found.dialect.delimiter, b'|');
+        let tape = found.builder().build_from_bytes(data.to_vec()).unwrap();
+        assert_eq!(tape.header(), &vec!["id".to_string(), "name".into()]);
+        assert_eq!((&tape).seek_field(0, 1).unwrap(), Some("a,b"));
+
+        let found = sniff_details(b"sep=;\n1;2\n3;4\n");
+        let tape = found.builder().build_from_bytes(&b"sep=;\n1;2\n3;4\n"[..]);
+        let tape = tape.unwrap();
+        assert_eq!(tape.header()[0], "field_0");
+        assert_eq!(tape.record_cnt, 3);
+    }
+}
@@ -0,0 +1,391 @@
+///
+/// SQL from a Tape
+///
+/// A `CREATE TABLE` statement from a Schema, and the records as batched `INSERT` statements or
+/// as the text format of PostgreSQL's `COPY ... FROM stdin`.  The records are read from the
+/// index one field at a time and written as they are read; no row is built.
+///
+///   Integer -> BIGINT, Float -> DOUBLE PRECISION (REAL), Boolean -> BOOLEAN (INTEGER)
+///   Date -> DATE, DateTime -> TIMESTAMP (TEXT in SQLite), YearMonth -> CHAR(7)
+///   Categorical, Text -> VARCHAR(max_len)
+///
+/// The typed values are written in their plain form (see `NumberFormat`, `Date`).
+///
+/// ⚠️  The lengths and the NOT NULL constraints describe the records that were scanned (see
+///    `SampleSpec`); a value that does not parse to the type of its column is an error.
+///
+/// 🔑 The columns are matched to the fields by name; a declared Schema may reorder or omit
+///    columns.
+///
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::{BufWriter, Write};
+
+use crate::error::StructureError;
+use crate::records::Record;
+use crate::schema::{ColumnType, Schema};
+use crate::tape::Tape;
+
+/// The number of records of an INSERT statement
+pub const BATCH_SIZE: usize = 500;
+
+/// The database that reads the statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SqlFlavor {
+    #[default]
+    Postgres,
+    Sqlite,
+}
+
+/// The statements of a table described by a Schema
+#[derive(Debug, Clone, Copy)]
+pub struct SqlDump<'a> {
+    table: &'a str,
+    schema: &'a Schema,
+    flavor: SqlFlavor,
+    batch_size: usize,
+}
+
+/// A value of a field, ready to be written
+enum Literal<'tape> {
+    Null,
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Text(Cow<'tape, str>),
+}
+
+impl<'a> SqlDump<'a> {
+    pub fn new(table: &'a str, schema: &'a Schema, flavor: SqlFlavor) -> Self {
+        SqlDump {
+            table,
+            schema,
+            flavor,
+            batch_size: BATCH_SIZE,
+        }
+    }
+    /// The number of records of an INSERT statement (at least 1)
+    pub fn batch_size(self, batch_size: usize) -> Self {
+        SqlDump {
+            batch_size: batch_size.max(1),
+            ..self
+        }
+    }
+    /// CREATE TABLE "table" ( "column" TYPE [NOT NULL], ... );
+    pub fn create_table(&self) -> String {
+        let mut sql = format!("CREATE TABLE {} (\n", quote_ident(self.table));
+        for (field_idx, column) in self.schema.columns.iter().enumerate() {
+            let separator = match field_idx {
+                0 => "",
+                _ => ",\n",
+            };
+            // infallible: writes to a String
+            let _ = write!(
+                sql,
+                "{}    {} {}",
+                separator,
+                quote_ident(&column.name),
+                self.sql_type(column.column_type, column.max_len)
+            );
+            if !column.nullable {
+                sql.push_str(" NOT NULL");
+            }
+        }
+        sql.push_str("\n);\n");
+        sql
+    }
+    /// INSERT INTO "table" ("column", ...) VALUES (...), ...; one statement per batch
+    pub fn write_inserts<W: Write>(
+        &self,
+        tape: &Tape,
+        out: W,
+    ) -> Result<(), StructureError> {
+        let field_idxs = self.field_idxs(tape)?;
+        let mut out = BufWriter::new(out);
+        let columns = self.column_list();
+        let records = tape.as_records();
+        let record_cnt = records.len();
+        for (nth, record) in records.enumerate() {
+            match nth % self.batch_size {
+                0 => write!(
+                    out,
+                    "INSERT INTO {} ({}) VALUES\n(",
+                    quote_ident(self.table),
+                    columns
+                )?,
+                _ => out.write_all(b",\n(")?,
+            }
+            for (nth, (column, &field_idx)) in
+                self.schema.columns.iter().zip(&field_idxs).enumerate()
+            {
+                if nth > 0 {
+                    out.write_all(b", ")?;
+                }
+                match literal(&record, field_idx, column.column_type)? {
+                    Literal::Null => out.write_all(b"NULL")?,
+                    Literal::Integer(value) => write!(out, "{}", value)?,
+                    Literal::Float(value) if value.is_finite() => {
+                        write!(out, "{:?}", value)?
+                    }
+                    // ⚠️  quoted: 'NaN', 'Infinity', '-Infinity'
+                    Literal::Float(value) => {
+                        write!(out, "'{}'", non_finite(value))?
+                    }
+                    Literal::Boolean(value) => {
+                        out.write_all(self.boolean(value).as_bytes())?
+                    }
+                    Literal::Text(value) => {
+                        out.write_all(b"'")?;
+                        out.write_all(value.replace('\'', "''").as_bytes())?;
+                        out.write_all(b"'")?;
+                    }
+                }
+            }
+            out.write_all(b")")?;
+            if (nth + 1) % self.batch_size == 0 || nth + 1 == record_cnt {
+                out.write_all(b";\n")?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+    /// COPY "table" ("column", ...) FROM stdin; the records, tab-separated; and `\.`
+    ///
+    /// 🚧 PostgreSQL only; the flavor is ignored.
+    pub fn write_copy<W: Write>(
+        &self,
+        tape: &Tape,
+        out: W,
+    ) -> Result<(), StructureError> {
+        let field_idxs = self.field_idxs(tape)?;
+        let mut out = BufWriter::new(out);
+        writeln!(
+            out,
+            "COPY {} ({}) FROM stdin;",
+            quote_ident(self.table),
+            self.column_list()
+        )?;
+        for record in tape.as_records() {
+            for (nth, (column, &field_idx)) in
+                self.schema.columns.iter().zip(&field_idxs).enumerate()
+            {
+                if nth > 0 {
+                    out.write_all(b"\t")?;
+                }
+                match literal(&record, field_idx, column.column_type)? {
+                    Literal::Null => out.write_all(b"\\N")?,
+                    Literal::Integer(value) => write!(out, "{}", value)?,
+                    Literal::Float(value) if value.is_finite() => {
+                        write!(out, "{:?}", value)?
+                    }
+                    Literal::Float(value) => {
+                        out.write_all(non_finite(value).as_bytes())?
+                    }
+                    Literal::Boolean(value) => {
+                        out.write_all(if value { b"t" } else { b"f" })?
+                    }
+                    Literal::Text(value) => copy_escaped(&mut out, &value)?,
+                }
+            }
+            out.write_all(b"\n")?;
+        }
+        out.write_all(b"\\.\n")?;
+        out.flush()?;
+        Ok(())
+    }
+    /// The field of each column of the schema, by name
+    fn field_idxs(&self, tape: &Tape) -> Result<Vec<usize>, StructureError> {
+        self.schema
+            .columns
+            .iter()
+            .map(|column| tape.field_index(&column.name))
+            .collect()
+    }
+    fn column_list(&self) -> String {
+        self.schema
+            .columns
+            .iter()
+            .map(|column| quote_ident(&column.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+    fn sql_type(&self, column_type: ColumnType, max_len: u32) -> String {
+        use SqlFlavor::*;
+        let sql_type = match (column_type, self.flavor) {
+            (ColumnType::Integer, _) => "BIGINT",
+            (ColumnType::Float, Postgres) => "DOUBLE PRECISION",
+            (ColumnType::Float, Sqlite) => "REAL",
+            (ColumnType::Boolean, Postgres) => "BOOLEAN",
+            (ColumnType::Boolean, Sqlite) => "INTEGER",
+            (ColumnType::Date, Postgres) => "DATE",
+            (ColumnType::DateTime, Postgres) => "TIMESTAMP",
+            (ColumnType::Date | ColumnType::DateTime, Sqlite) => "TEXT",
+            (ColumnType::YearMonth, _) => "CHAR(7)",
+            (ColumnType::Categorical | ColumnType::Text, _) if max_len > 0 => {
+                return format!("VARCHAR({})", max_len)
+            }
+            (ColumnType::Categorical | ColumnType::Text, _) => "TEXT",
+        };
+        sql_type.to_string()
+    }
+    fn boolean(&self, value: bool) -> &'static str {
+        match (self.flavor, value) {
+            (SqlFlavor::Postgres, true) => "TRUE",
+            (SqlFlavor::Postgres, false) => "FALSE",
+            (SqlFlavor::Sqlite, true) => "1",
+            (SqlFlavor::Sqlite, false) => "0",
+        }
+    }
+}
+
+/// The field as a value of the type of its column; a missing field is null.
+fn literal<'tape>(
+    record: &Record<'tape>,
+    field_idx: usize,
+    column_type: ColumnType,
+) -> Result<Literal<'tape>, StructureError> {
+    if record.is_null(field_idx) {
+        return Ok(Literal::Null);
+    }
+    let literal = match column_type {
+        ColumnType::Integer => record.get_i64(field_idx)?.map(Literal::Integer),
+        ColumnType::Float => record.get_f64(field_idx)?.map(Literal::Float),
+        ColumnType::Boolean => {
+            record.get_bool(field_idx)?.map(Literal::Boolean)
+        }
+        ColumnType::Date => record
+            .get_date(field_idx)?
+            .map(|date| Literal::Text(date.to_string().into())),
+        ColumnType::YearMonth => record
+            .get_year_month(field_idx)?
+            .map(|month| Literal::Text(month.to_string().into())),
+        ColumnType::DateTime => record
+            .get_datetime(field_idx)?
+            .map(|datetime| Literal::Text(datetime.to_string().into())),
+        ColumnType::Categorical | ColumnType::Text => {
+            record.value(field_idx).map(Literal::Text)
+        }
+    };
+    Ok(literal.unwrap_or(Literal::Null))
+}
+
+/// "NPI Number" -> "\"NPI Number\""
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn non_finite(value: f64) -> &'static str {
+    match value {
+        _ if value.is_nan() => "NaN",
+        _ if value > 0.0 => "Infinity",
+        _ => "-Infinity",
+    }
+}
+
+/// The escapes of the COPY text format: backslash, tab, newline and carriage return
+fn copy_escaped<W: Write>(out: &mut W, value: &str) -> std::io::Result<()> {
+    let bytes = value.as_bytes();
+    let mut start = 0;
+    for (pos, &byte) in bytes.iter().enumerate() {
+        let escape: &[u8] = match byte {
+            b'\\' => b"\\\\",
+            b'\t' => b"\\t",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            _ => continue,
+        };
+        out.write_all(&bytes[start..pos])?;
+        out.write_all(escape)?;
+        start = pos + 1;
+    }
+    out.write_all(&bytes[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create, Encoding, SampleSpec, TapeBuilder};
+
+    #[test]
+    fn sample_rx() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        let dump = SqlDump::new("rx", &schema, SqlFlavor::Postgres);
+        let ddl = dump.create_table();
+        assert!(ddl.starts_with("CREATE TABLE \"rx\" (\n"));
+        assert!(ddl.contains("    \"NPI Number\" BIGINT NOT NULL,\n"));
+        assert!(ddl.contains("    \"Year-Month\" CHAR(7) NOT NULL,\n"));
+        assert!(ddl.contains("\"Primary Specialty Desc\" VARCHAR("));
+        assert_eq!(ddl, tape.create_table("rx", SqlFlavor::Postgres));
+
+        let mut sql = Vec::new();
+        dump.batch_size(3).write_inserts(&tape, &mut sql).unwrap();
+        let sql = String::from_utf8(sql).unwrap();
+        assert_eq!(sql.matches("INSERT INTO \"rx\"").count(), 3);
+        assert_eq!(sql.matches(";\n").count(), 3);
+        assert!(sql.contains(
+            "'INTERNAL MED, CARD. ELECTROGY', 'F', 3, 'CASH,IT', '2016-09', 1)"
+        ));
+    }
+    #[test]
+    fn literals() {
+        let data = "id,name,score,active,note\n1,O'Brien,1.5,yes,\"a\tb\\c\"\n2,,,no,\n";
+        let tape = TapeBuilder::new()
+            .build_from_bytes(data.as_bytes().to_vec())
+            .unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        let dump = SqlDump::new("t", &schema, SqlFlavor::Sqlite);
+        assert!(dump.create_table().contains("\"active\" INTEGER NOT NULL"));
+
+        let mut sql = Vec::new();
+        dump.write_inserts(&tape, &mut sql).unwrap();
+        assert_eq!(
+            String::from_utf8(sql).unwrap(),
+            "INSERT INTO \"t\" (\"id\", \"name\", \"score\", \"active\", \"note\") VALUES\n\
+             (1, 'O''Brien', 1.5, 1, 'a\tb\\c'),\n\
+             (2, NULL, NULL, 0, NULL);\n"
+        );
+        let mut copy = Vec::new();
+        dump.write_copy(&tape, &mut copy).unwrap();
+        assert_eq!(
+            String::from_utf8(copy).unwrap(),
+            "COPY \"t\" (\"id\", \"name\", \"score\", \"active\", \"note\") FROM stdin;\n\
+             1\tO'Brien\t1.5\tt\ta\\tb\\\\c\n\
+             2\t\\N\t\\N\tf\t\\N\n\
+             \\.\n"
+        );
+    }
+    #[test]
+    fn lengths_of_the_written_values() {
+        let data = b"name,n\n  abcd  ,1\nab,2\n";
+        let tape = TapeBuilder::new().build_from_bytes(&data[..]).unwrap();
+        let schema = tape.infer_schema(SampleSpec::All);
+        let dump = SqlDump::new("t", &schema, SqlFlavor::Postgres);
+        assert!(dump.create_table().contains("\"name\" VARCHAR(8) NOT NULL"));
+        let mut sql = Vec::new();
+        dump.write_inserts(&tape, &mut sql).unwrap();
+        assert!(String::from_utf8(sql).unwrap().contains("('  abcd  ', 1)"));
+    }
+    #[test]
+    fn declared_columns() {
+        let data = b"name,n,note\nab,1,x\ncd,2,y\n";
+        let tape = TapeBuilder::new().build_from_bytes(&data[..]).unwrap();
+        let schema = Schema::from_text("n: integer\nname: text\n").unwrap();
+        let dump = SqlDump::new("t", &schema, SqlFlavor::Postgres);
+        let mut sql = Vec::new();
+        dump.write_inserts(&tape, &mut sql).unwrap();
+        assert_eq!(
+            String::from_utf8(sql).unwrap(),
+            "INSERT INTO \"t\" (\"n\", \"name\") VALUES\n(1, 'ab'),\n(2, 'cd');\n"
+        );
+        let mut copy = Vec::new();
+        dump.write_copy(&tape, &mut copy).unwrap();
+        assert!(String::from_utf8(copy)
+            .unwrap()
+            .contains("\n1\tab\n2\tcd\n"));
+
+        let schema = Schema::from_text("id: integer\n").unwrap();
+        let dump = SqlDump::new("t", &schema, SqlFlavor::Postgres);
+        assert!(dump.write_inserts(&tape, Vec::new()).is_err());
+    }
+}
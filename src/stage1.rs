@@ -13,7 +13,13 @@
 ///          * value = io::offset
 ///
 use std::arch::x86_64::*;
+use std::convert::TryFrom;
 use std::mem;
+use std::ops::Range;
+
+use crate::builder::IndexWidth;
+use crate::dialect::{Dialect, Escape};
+use crate::error::StructureError;
 
 /// default bit-count size
 /// (for a given bit-set, the bit-count tags whether the 16-bit value is a member of the set)
@@ -55,10 +61,82 @@ macro_rules! print_bitset_lookup {
     };
 }
 
+/// The class of each byte (see `print_bitset_lookup!`)
+const NEWLINE: u8 = 1;
+const DELIMITER: u8 = 2;
+const SPACE: u8 = 4;
+const ESCAPE: u8 = 8;
+const QUOTE: u8 = 16;
+
+/// The nibble lookup tables, computed for a dialect.
+///
+/// A byte is in a class when both the entry for its low nibble, and the entry for its high
+/// nibble have the bit of the class.  With the default dialect the tables are
+/// `low_nibble_mask!` and `high_nibble_mask!`.
+///
+/// ⚠️  Each class other than the newline hosts a single byte; otherwise a combination of the
+///    nibbles of two bytes could report a byte that is not in the class.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Classifier {
+    pub(crate) lo: __m128i,
+    pub(crate) hi: __m128i,
+}
+
+impl Classifier {
+    pub(crate) fn new(dialect: &Dialect) -> Self {
+        let escape = match dialect.escape {
+            Escape::Backslash(escape) => escape,
+            _ => b'\\',
+        };
+        let mut lo = [0_u8; 16];
+        let mut hi = [0_u8; 16];
+        for (byte, class) in [
+            (b'\r', NEWLINE),
+            (b'\n', NEWLINE),
+            (dialect.delimiter, DELIMITER),
+            (b' ', SPACE),
+            (escape, ESCAPE),
+            (dialect.quote, QUOTE),
+        ] {
+            lo[(byte & 0xf) as usize] |= class;
+            hi[(byte >> 4) as usize] |= class;
+        }
+        unsafe {
+            Classifier {
+                lo: _mm_loadu_si128(lo.as_ptr() as *const __m128i),
+                hi: _mm_loadu_si128(hi.as_ptr() as *const __m128i),
+            }
+        }
+    }
+}
+impl Default for Classifier {
+    fn default() -> Self {
+        unsafe {
+            Classifier {
+                lo: low_nibble_mask!(),
+                hi: high_nibble_mask!(),
+            }
+        }
+    }
+}
+
 /// The representation of the csv structure. The index value is the offset in code-units for UTF8.
 /// The code-point values represent record and field delimiters.
-#[derive(Debug)]
-pub struct StructureIndex(pub Vec<CodeUnitPos>);
+///
+/// The offsets are stored as usize, or as u32 for data under 4 GiB (see `IndexWidth`).
+#[derive(Debug, PartialEq)]
+pub enum StructureIndex {
+    Usize(Vec<CodeUnitPos>),
+    U32(Vec<u32>),
+}
+
+/// A run of the `StructureIndex` (e.g., the slots of a record); the offsets read as usize
+/// whatever the width of the index.
+#[derive(Debug, Clone, Copy)]
+pub enum Slots<'index> {
+    Usize(&'index [CodeUnitPos]),
+    U32(&'index [u32]),
+}
 
 /// The memory offset position of a code-unit. The collection of these values is hosted in the
 /// `StructureIndex`. The min and max values must fall within the range of the memory hosting Data.
@@ -130,31 +208,157 @@ impl std::fmt::Display for StructureIndex {
         let len = self.len();
         write!(
             f,
-            "StructureIndex len: {} first: {} last: {}",
+            "StructureIndex len: {} first: {:?} last: {:?}",
             len,
-            self.0[0],
-            self.0[len - 1]
+            self.get(0),
+            self.get(len.wrapping_sub(1))
         )
     }
 }
 
-impl std::ops::Deref for StructureIndex {
-    type Target = Vec<CodeUnitPos>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
+impl StructureIndex {
+    /// The index as a whole
+    pub fn slots(&self) -> Slots<'_> {
+        match self {
+            StructureIndex::Usize(index) => Slots::Usize(index),
+            StructureIndex::U32(index) => Slots::U32(index),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.slots().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The offset of a key
+    pub fn get(&self, key: usize) -> Option<usize> {
+        self.slots().get(key)
+    }
+    /// # Safety
+    ///
+    /// `key` must be less than `len`.
+    pub unsafe fn get_unchecked(&self, key: usize) -> usize {
+        self.slots().get_unchecked(key)
+    }
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = usize> + ExactSizeIterator + '_ {
+        self.slots().iter()
+    }
+    pub fn width(&self) -> IndexWidth {
+        match self {
+            StructureIndex::Usize(_) => IndexWidth::Usize,
+            StructureIndex::U32(_) => IndexWidth::U32,
+        }
+    }
+    /// usize -> u32; halves the memory of the index.  Err when an offset exceeds u32.
+    pub(crate) fn narrow(self) -> Result<StructureIndex, StructureError> {
+        match self {
+            StructureIndex::Usize(index) => index
+                .iter()
+                .map(|pos| u32::try_from(**pos))
+                .collect::<Result<Vec<_>, _>>()
+                .map(StructureIndex::U32)
+                .map_err(|_| StructureError::InvalidOption {
+                    option: "index_width",
+                    reason: "an offset exceeds u32".to_string(),
+                }),
+            narrow => Ok(narrow),
+        }
+    }
+    /// Give up the allocation, e.g., to recycle it; None when u32
+    pub(crate) fn into_vec(self) -> Option<Vec<usize>> {
+        match self {
+            StructureIndex::Usize(index) => {
+                Some(bytemuck::allocation::cast_vec(index))
+            }
+            StructureIndex::U32(_) => None,
+        }
+    }
+    /// index of data[offset..] -> index of data; the first value points to the byte before
+    /// the header (the end of the preamble).
+    pub(crate) fn shift(&mut self, offset: usize) {
+        if offset == 0 {
+            return;
+        }
+        match self {
+            StructureIndex::Usize(index) => {
+                *index[0] = offset - 1;
+                index.iter_mut().skip(1).for_each(|pos| **pos += offset);
+            }
+            StructureIndex::U32(index) => {
+                let offset = offset as u32;
+                index[0] = offset - 1;
+                index.iter_mut().skip(1).for_each(|pos| *pos += offset);
+            }
+        }
     }
 }
-impl std::ops::DerefMut for StructureIndex {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+
+impl<'index> Slots<'index> {
+    pub fn len(&self) -> usize {
+        match self {
+            Slots::Usize(slots) => slots.len(),
+            Slots::U32(slots) => slots.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The offset of a key
+    pub fn get(&self, key: usize) -> Option<usize> {
+        match self {
+            Slots::Usize(slots) => slots.get(key).map(|pos| **pos),
+            Slots::U32(slots) => slots.get(key).map(|&pos| pos as usize),
+        }
+    }
+    /// # Safety
+    ///
+    /// `key` must be less than `len`.
+    pub unsafe fn get_unchecked(&self, key: usize) -> usize {
+        match self {
+            Slots::Usize(slots) => **slots.get_unchecked(key),
+            Slots::U32(slots) => *slots.get_unchecked(key) as usize,
+        }
+    }
+    /// The slots start..end; panics when out of range (as a slice)
+    pub fn range(&self, range: Range<usize>) -> Slots<'index> {
+        match self {
+            Slots::Usize(slots) => Slots::Usize(&slots[range]),
+            Slots::U32(slots) => Slots::U32(&slots[range]),
+        }
+    }
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = usize> + ExactSizeIterator + 'index
+    {
+        let slots = *self;
+        // safe: key < len
+        (0..self.len()).map(move |key| unsafe { slots.get_unchecked(key) })
+    }
+    /// The first key for which `pred` is false (see `slice::partition_point`)
+    pub fn partition_point(
+        &self,
+        mut pred: impl FnMut(usize) -> bool,
+    ) -> usize {
+        match self {
+            Slots::Usize(slots) => slots.partition_point(|pos| pred(**pos)),
+            Slots::U32(slots) => {
+                slots.partition_point(|&pos| pred(pos as usize))
+            }
+        }
     }
 }
 // ------------------------------------------------------------------------------
 
 /// Trait interface for processing the first csv processing stage.
 pub(crate) trait Stage1<T> {
-    fn structure(&self, structure: &mut u64, in_str: &mut i64);
+    fn structure(
+        &self,
+        classifier: &Classifier,
+        structure: &mut u64,
+        in_str: &mut i64,
+    );
     fn show(&self);
     /// Decode the set of bits from set_bits to the acc array
     /// (64-bits -> array with len + ??)
@@ -184,7 +388,7 @@ pub(crate) trait Stage1<T> {
 
         // task
         // consume set_bits by removing the least set bet with every iteration
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "trace")]
         {
             println!("Computing the index...");
             println!("Start value:   {:#066b}", set_bits);
@@ -227,12 +431,12 @@ pub(crate) trait Stage1<T> {
             let mut shift = 0;
             // consume the set_bits to zero
             while set_bits != 0 {
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("codepoint value: {:?}", codepoint_cnt);
                 // count leading zeros
                 *ptr.add(base + 0 + shift) =
                     codepoint_cnt + set_bits.trailing_zeros() as usize;
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("set_bits zero: {:#066b}", set_bits);
                 // generate the next value of set_bits (bringing it closer to zero)
                 // clear away the lowest set bit remove least set bit
@@ -240,57 +444,57 @@ pub(crate) trait Stage1<T> {
 
                 *ptr.add(base + 1 + shift) =
                     codepoint_cnt + set_bits.trailing_zeros() as usize;
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("set_bits zero: {:#066b}", set_bits);
                 set_bits &= set_bits.saturating_sub(1);
 
                 *ptr.add(base + 2 + shift) =
                     codepoint_cnt + set_bits.trailing_zeros() as usize;
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("set_bits zero: {:#066b}", set_bits);
                 set_bits &= set_bits.saturating_sub(1);
 
                 *ptr.add(base + 3 + shift) =
                     codepoint_cnt + set_bits.trailing_zeros() as usize;
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("set_bits zero: {:#066b}", set_bits);
                 set_bits &= set_bits.saturating_sub(1);
 
                 *ptr.add(base + 4 + shift) =
                     codepoint_cnt + set_bits.trailing_zeros() as usize;
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("set_bits zero: {:#066b}", set_bits);
                 set_bits &= set_bits.saturating_sub(1);
 
                 *ptr.add(base + 5 + shift) =
                     codepoint_cnt + set_bits.trailing_zeros() as usize;
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("set_bits zero: {:#066b}", set_bits);
                 set_bits &= set_bits.saturating_sub(1);
 
                 *ptr.add(base + 6 + shift) =
                     codepoint_cnt + set_bits.trailing_zeros() as usize;
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("set_bits zero: {:#066b}", set_bits);
                 set_bits &= set_bits.saturating_sub(1);
 
                 *ptr.add(base + 7 + shift) =
                     codepoint_cnt + set_bits.trailing_zeros() as usize;
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("set_bits zero: {:#066b}", set_bits);
                 set_bits &= set_bits.saturating_sub(1);
 
                 // report the acc
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("acc: {:?}", acc);
-                #[cfg(debug_assertions)]
+                #[cfg(feature = "trace")]
                 println!("next base: {:?}", next_base);
                 *array_idx = *array_idx + 8;
                 shift = shift + 8;
             }
             acc.set_len(next_base);
             *array_idx = next_base as u32;
-            #[cfg(debug_assertions)]
+            #[cfg(feature = "trace")]
             println!("acc len: {:?}", acc.len());
         }
     }
@@ -305,7 +509,7 @@ fn get_struct_positions(search: u8, res0: __m128i) -> u64 {
         let struct_mask: __m128i = _mm_set1_epi8(search as i8);
         let struc = _mm_and_si128(res0, struct_mask);
 
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "trace")]
         {
             // show result for the single vector
             println!("----------------------------------------------------------------------------------");
@@ -347,14 +551,19 @@ impl SimdInputFragment {
 }
 
 impl Stage1<__m128i> for SimdInputFragment {
-    fn structure(&self, structure: &mut u64, in_string: &mut i64) {
+    fn structure(
+        &self,
+        classifier: &Classifier,
+        structure: &mut u64,
+        in_string: &mut i64,
+    ) {
         //
         // ⬜ Make the structure u64 generic; where we need u16 or i32
         //
         unsafe {
-            // constant vectors
-            let lo_nibble_mask: __m128i = low_nibble_mask!();
-            let hi_nibble_mask: __m128i = high_nibble_mask!();
+            // lookup vectors
+            let lo_nibble_mask: __m128i = classifier.lo;
+            let hi_nibble_mask: __m128i = classifier.hi;
             let low_mask: __m128i = _mm_set1_epi8(0xf);
             // let zero: __m128i = _mm_set1_epi8(0x0); //0b11
 
@@ -424,7 +633,7 @@ impl Stage1<__m128i> for SimdInputFragment {
             *in_string = _mm_cvtsi128_si64(string_mask) as i64 >> 63;
 
             // ⚠️  only the first 16-bits are relevant
-            #[cfg(debug_assertions)]
+            #[cfg(feature = "trace")]
             {
                 println!("----------------------------------------------------------------------------------");
                 println!("🦀 structure result WIP");
@@ -453,12 +662,10 @@ impl Stage1<__m128i> for SimdInputFragment {
             // show the string representation the data
             let tmp00 = _mm_extract_epi64(self.v, 0);
             let tmp00 = tmp00.to_le_bytes();
-            let tmp00 =
-                std::str::from_utf8(&tmp00).expect("Fond invalid UTF-8");
+            let tmp00 = String::from_utf8_lossy(&tmp00);
             let tmp01 = _mm_extract_epi64(self.v, 1);
             let tmp01 = tmp01.to_le_bytes();
-            let tmp01 =
-                std::str::from_utf8(&tmp01).expect("Fond invalid UTF-8");
+            let tmp01 = String::from_utf8_lossy(&tmp01);
 
             println!("{}{}", tmp00, tmp01);
         }
@@ -467,7 +674,7 @@ impl Stage1<__m128i> for SimdInputFragment {
 /// LineEnding is an alternative name.  The approach used by Rust is the search for \n, then remove
 /// the \r at the end of each line.
 ///
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[non_exhaustive]
 pub enum NewLine {
     /// move down to the next line; move to the beginning
@@ -1,13 +1,34 @@
-/// ⬜ Formally apply the builder patter
+/// 📚 The builder pattern: `TapeBuilder` (builder.rs)
 /// https://rust-unofficial.github.io/patterns/patterns/builder.html
 ///
 // use bytemuck::cast;
 use memmap::Mmap;
+use std::borrow::Cow;
 use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
 
+use serde::Deserialize;
+
+use crate::column::{Column, ColumnKey};
+use crate::counts::{self, ValueCounts};
+use crate::de::DeserializeRecords;
+use crate::dialect::Dialect;
+use crate::encoding::Encoding;
 use crate::error::StructureError;
+use crate::guard::{FileLock, SourceStamp};
+use crate::header_map::{HeaderMap, NameMatching};
+use crate::nulls::NullValues;
+use crate::number_format::NumberFormat;
+use crate::reader;
 use crate::record_source::{RecordSource, WithRecordSource};
+use crate::records::{Record, Records};
+use crate::scalar;
+use crate::schema::{self, SampleSpec, Schema};
+use crate::sql::{SqlDump, SqlFlavor};
 use crate::stage1::{KeyToPos, NewLine, StructureIndex};
+use crate::validate::{self, ValidationReport};
+use crate::view::{Projection, TapeView};
 
 /// Atomic representation of how to utilize the tape in a parallel-processing context.
 pub struct Chunk<'index> {
@@ -32,51 +53,105 @@ impl<'index> fmt::Debug for Chunk<'index> {
             .field("records", &self.record_cnt)
             .field("record first", &start_idx)
             .field("last", &end_idx)
-            .field("index first", &(self.index[*self.start]))
-            .field("last", &(self.index[*self.end]))
+            .field("index first", &self.index.get(*self.start))
+            .field("last", &self.index.get(*self.end))
             .finish()
     }
 }
 pub type Chunks<'index> = Vec<Chunk<'index>>;
 
 /// A slice representation of the data source.  The stride of each index is u8 representing UTF8.
-pub struct DataBytes(Mmap);
+/// The bytes are either shared with the file (memory map), or hosted in memory.
+///
+/// 🔑 Cheap to clone; the bytes are shared, never copied.
+#[derive(Clone)]
+pub enum DataBytes {
+    Mapped(Arc<Mmap>),
+    /// e.g., a copy of a file, a network payload, decompressed data
+    Owned(Arc<Vec<u8>>),
+    Shared(Arc<[u8]>),
+    Static(&'static [u8]),
+}
 // ------------------------------------------------------------------------------
 // Data trait implementations
 // ------------------------------------------------------------------------------
 impl fmt::Display for DataBytes {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let len = self.0.len();
+        let len = self.len();
         write!(
             f,
             "DataBytes len: {} first: {} last: {}",
             len,
-            self.0[0],
-            self.0[len - 1]
+            self[0],
+            self[len - 1]
         )
     }
 }
+impl fmt::Debug for DataBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let host = match self {
+            DataBytes::Mapped(_) => "Mapped",
+            DataBytes::Owned(_) => "Owned",
+            DataBytes::Shared(_) => "Shared",
+            DataBytes::Static(_) => "Static",
+        };
+        f.debug_struct("DataBytes")
+            .field("host", &host)
+            .field("len", &self.len())
+            .finish()
+    }
+}
 impl std::ops::Deref for DataBytes {
-    type Target = Mmap;
+    type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        match self {
+            DataBytes::Mapped(memmap) => memmap,
+            DataBytes::Owned(bytes) => bytes,
+            DataBytes::Shared(bytes) => bytes,
+            DataBytes::Static(bytes) => bytes,
+        }
+    }
+}
+impl From<Mmap> for DataBytes {
+    fn from(memmap: Mmap) -> Self {
+        DataBytes::Mapped(Arc::new(memmap))
     }
 }
-impl std::ops::DerefMut for DataBytes {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl From<Arc<Mmap>> for DataBytes {
+    fn from(memmap: Arc<Mmap>) -> Self {
+        DataBytes::Mapped(memmap)
+    }
+}
+impl From<Vec<u8>> for DataBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        DataBytes::Owned(Arc::new(bytes))
+    }
+}
+impl From<Arc<[u8]>> for DataBytes {
+    fn from(bytes: Arc<[u8]>) -> Self {
+        DataBytes::Shared(bytes)
+    }
+}
+impl From<&'static [u8]> for DataBytes {
+    fn from(bytes: &'static [u8]) -> Self {
+        DataBytes::Static(bytes)
     }
 }
 // ------------------------------------------------------------------------------
 
 /// External-facing version of TapeCore
+///
+/// 🔑 A clone shares the bytes, the index and the header (and the lock, if any).
+#[derive(Clone)]
 pub struct Tape {
-    pub header: Header,
+    pub header: Arc<Header>,
     pub record_cnt: u32,
     pub record_jump_size: KeyToPos,
     bytes: DataBytes,
-    index: StructureIndex,
+    index: Arc<StructureIndex>,
+    stamp: Option<SourceStamp>,
+    lock: Option<Arc<FileLock>>,
 }
 
 impl Tape {
@@ -85,13 +160,67 @@ impl Tape {
         core.init()?;
 
         Ok(Tape {
-            header: core.header,
-            bytes: DataBytes(core.memmap),
+            header: Arc::new(core.header),
+            bytes: core.bytes,
             record_cnt: core.record_cnt.unwrap(), // safe with init
             record_jump_size: core.record_jump_size.unwrap(), // safe with init
-            index: core.index,
+            index: Arc::new(core.index),
+            stamp: None,
+            lock: None,
         })
     }
+    /// Record the state of the file hosting the data, and hold the lock (if any) for the life
+    /// of the Tape.
+    pub(crate) fn guarded(
+        self,
+        stamp: SourceStamp,
+        lock: Option<FileLock>,
+    ) -> Tape {
+        Tape {
+            stamp: Some(stamp),
+            lock: lock.map(Arc::new),
+            ..self
+        }
+    }
+    /// The allocations of the Tape that are not shared with a clone: the bytes (when owned) and
+    /// the index.
+    pub(crate) fn into_buffers(self) -> (Option<Vec<u8>>, Option<Vec<usize>>) {
+        let bytes = match self.bytes {
+            DataBytes::Owned(bytes) => Arc::try_unwrap(bytes).ok(),
+            _ => None,
+        };
+        let index = Arc::try_unwrap(self.index).ok().and_then(|i| i.into_vec());
+        (bytes, index)
+    }
+    /// Cheap check (size and modification time) for whether the file hosting the data has
+    /// changed since the Tape was created.  Always false when the Tape does not depend on a file.
+    pub fn is_stale(&self) -> bool {
+        match (&self.bytes, &self.stamp) {
+            (DataBytes::Mapped(_), Some(stamp)) => stamp.is_stale(),
+            _ => false,
+        }
+    }
+    /// `is_stale` as an error; a long-running process can call this before reading.
+    pub fn ensure_fresh(&self) -> Result<(), StructureError> {
+        match (self.is_stale(), &self.stamp) {
+            (true, Some(stamp)) => Err(StructureError::StaleSource {
+                path: stamp.path.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+    /// Compare the index with the one computed by the reference (scalar) reader
+    pub fn verify(&self) -> Result<(), StructureError> {
+        let preamble = self.header.preamble();
+        let mut reference =
+            scalar::read_with(&self.bytes[preamble..], &self.header.dialect);
+        reference.shift(preamble);
+        reader::compare(&self.bytes, &self.index, &reference)
+    }
+    /// The data bytes are protected by an advisory lock
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
     pub fn chunks<'index>(
         &'index self,
         num: u8,
@@ -144,12 +273,142 @@ impl Tape {
     pub fn bytes(&self) -> &DataBytes {
         &self.bytes
     }
-    pub fn as_records(&self) -> WithRecordSource<&Tape> {
-        todo!()
+    /// Iterate over the records (excludes the header)
+    pub fn as_records(&self) -> Records<'_> {
+        Records::new(self)
+    }
+    /// Iterate over the records deserialized to `T`; a struct selects the fields by name, a
+    /// tuple by position.
+    pub fn deserialize<'tape, T: Deserialize<'tape>>(
+        &'tape self,
+    ) -> DeserializeRecords<'tape, T> {
+        DeserializeRecords::new(self.as_records(), self.header())
+    }
+    /// A view of one field across all of the records; select by position or name.
+    pub fn column<K: ColumnKey>(
+        &self,
+        key: K,
+    ) -> Result<Column<'_>, StructureError> {
+        Ok(Column::new(self, key.field_idx(&self.header)?))
     }
     pub fn header(&self) -> &Vec<String> {
         &self.header.header
     }
+    /// name -> position of the field
+    pub fn field_index(&self, name: &str) -> Result<usize, StructureError> {
+        self.header.field_index(name)
+    }
+    /// Classify the columns from a scan of the records (see `SampleSpec`)
+    pub fn infer_schema(&self, sample: SampleSpec) -> Schema {
+        schema::infer(self, sample)
+    }
+    /// Check the header and the values against a declared Schema; the chunks of the records
+    /// are checked in parallel.
+    pub fn validate(
+        &self,
+        schema: &Schema,
+    ) -> Result<ValidationReport, StructureError> {
+        validate::validate(self, schema, threads())
+    }
+    /// value -> number of records, for a column (see `ValueCounts`)
+    pub fn value_counts<K: ColumnKey>(
+        &self,
+        key: K,
+    ) -> Result<ValueCounts<Cow<'_, str>>, StructureError> {
+        let field_idx = key.field_idx(&self.header)?;
+        counts::count(self, threads(), |record| record.get_value(field_idx))
+    }
+    /// values -> number of records, for a combination of columns; a record with a null
+    /// value in any of the columns is counted as null.
+    pub fn value_counts_multi<K: ColumnKey>(
+        &self,
+        keys: &[K],
+    ) -> Result<ValueCounts<Vec<Cow<'_, str>>>, StructureError> {
+        let field_idxs = keys
+            .iter()
+            .map(|key| key.field_idx(&self.header))
+            .collect::<Result<Vec<_>, _>>()?;
+        counts::count(self, threads(), |record| {
+            field_idxs
+                .iter()
+                .map(|&field_idx| record.get_value(field_idx))
+                .collect()
+        })
+    }
+    /// (first record, number of records) of each of the chunks; none without records
+    pub(crate) fn record_ranges(
+        &self,
+        num: u8,
+    ) -> Result<Vec<(usize, usize)>, StructureError> {
+        if self.as_records().len() == 0 {
+            return Ok(Vec::new());
+        }
+        let jump = self.record_jump_size.0;
+        let ranges = self
+            .chunks(num.max(1))?
+            .iter()
+            .map(|chunk| (chunk.start.0 / jump - 1, chunk.record_cnt as usize))
+            .collect();
+        Ok(ranges)
+    }
+    /// A CREATE TABLE statement from a scan of all of the records (see `SqlDump`)
+    pub fn create_table(&self, table: &str, flavor: SqlFlavor) -> String {
+        let schema = self.infer_schema(SampleSpec::All);
+        SqlDump::new(table, &schema, flavor).create_table()
+    }
+    /// A view of a range of the records; None when out of range.
+    pub fn slice(&self, records: Range<u32>) -> Option<TapeView> {
+        TapeView::new(self).slice(records)
+    }
+    /// A view of a selection of the fields, in the order of the keys
+    pub fn project<K: ColumnKey>(
+        &self,
+        keys: &[K],
+    ) -> Result<Projection, StructureError> {
+        TapeView::new(self).project(keys)
+    }
+    /// How the names are matched; the header is copied when shared with a clone
+    pub fn set_name_matching(&mut self, matching: NameMatching) {
+        Arc::make_mut(&mut self.header).set_name_matching(matching);
+    }
+    /// How the numbers are written (e.g. `NumberFormat::european()`)
+    pub fn set_number_format(&mut self, number_format: NumberFormat) {
+        Arc::make_mut(&mut self.header).set_number_format(number_format);
+    }
+    /// The tokens that mean "missing" (e.g. `NullValues::common()`)
+    pub fn set_null_values(&mut self, nulls: NullValues) {
+        Arc::make_mut(&mut self.header).set_null_values(nulls);
+    }
+    /// The value of a field; None when the field is null, or the record does not exist.
+    pub fn seek_value(
+        &self,
+        record_idx: u32,
+        field_idx: usize,
+    ) -> Result<Option<Cow<'_, str>>, StructureError> {
+        if field_idx >= self.header.field_cnt as usize {
+            return Err(StructureError::FieldOutOfRange {
+                field: field_idx,
+                field_cnt: self.header.field_cnt,
+            });
+        }
+        Ok(self
+            .as_records()
+            .nth(record_idx as usize)
+            .and_then(|record| record.get_value(field_idx)))
+    }
+    /// The value of a field selected by name (see `Record::get_value`); None when the record
+    /// does not exist or the field is null.
+    pub fn seek_field_by_name(
+        &self,
+        record_idx: u32,
+        name: &str,
+    ) -> Result<Option<Cow<'_, str>>, StructureError> {
+        let field_idx = self.field_index(name)?;
+        Ok(self
+            .as_records()
+            .nth(record_idx as usize)
+            .and_then(|record| record.get_value(field_idx)))
+    }
 }
 
 impl RecordSource for &Tape {
@@ -169,7 +428,22 @@ impl RecordSource for &Tape {
         &self.header.new_line
     }
     fn data_bytes(&self) -> &[u8] {
-        (*self.bytes).as_ref()
+        &self.bytes
+    }
+    fn encoding(&self) -> Encoding {
+        self.header.encoding
+    }
+    fn dialect(&self) -> Dialect {
+        self.header.dialect
+    }
+}
+
+impl<'tape> IntoIterator for &'tape Tape {
+    type Item = Record<'tape>;
+    type IntoIter = Records<'tape>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_records()
     }
 }
 
@@ -185,7 +459,7 @@ impl fmt::Debug for Tape {
 pub struct TapeCore {
     header: Header,
     index: StructureIndex,
-    memmap: Mmap,
+    bytes: DataBytes,
     first_record_idx: Option<usize>,
     record_cnt: Option<u32>,
     record_jump_size: Option<KeyToPos>,
@@ -207,23 +481,45 @@ impl RecordSource for TapeCore {
         &self.header.new_line
     }
     fn data_bytes(&self) -> &[u8] {
-        &self.memmap
+        &self.bytes
+    }
+    fn encoding(&self) -> Encoding {
+        self.header.encoding
+    }
+    fn dialect(&self) -> Dialect {
+        self.header.dialect
     }
 }
 
 /// Vec of field names
-/// ⬜ The delimiter value is not referencing a single value and is fixed ','
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Header {
     pub header: Vec<String>,
-    new_line: NewLine,
+    pub(crate) new_line: NewLine,
     pub field_cnt: u32,
-    delimiter: u8, // utf8
+    dialect: Dialect,
     pub record_offset: u32,
+    encoding: Encoding,
+    preamble: usize,
+    map: HeaderMap,
+    number_format: NumberFormat,
+    nulls: NullValues,
 }
 
 impl Header {
-    pub fn new(memmap: &Mmap) -> Header {
+    pub fn new(memmap: &[u8]) -> Header {
+        Header::with_encoding(memmap, Encoding::Utf8)
+    }
+    /// The field names are decoded using the encoding of the data source.
+    pub fn with_encoding(memmap: &[u8], encoding: Encoding) -> Header {
+        Header::with_dialect(memmap, encoding, Dialect::default())
+    }
+    /// The field names are split and unquoted using the dialect.
+    pub fn with_dialect(
+        memmap: &[u8],
+        encoding: Encoding,
+        dialect: Dialect,
+    ) -> Header {
         // end of the header
         let header_end_idx = memmap
             .iter()
@@ -231,49 +527,105 @@ impl Header {
             .collect::<Vec<_>>()
             .len();
 
-        // Set the NewLine value
+        // Set the NewLine value; a header-only source may not end with one
         let mut new_line = NewLine::LF;
-        if memmap[header_end_idx + 1] == 0xa {
+        if memmap.get(header_end_idx + 1) == Some(&0xa) {
             new_line = NewLine::CRLF;
         };
 
         // skip the bit-order-marker (if exists)
-        let header_start_idx = memmap
-            .iter()
-            .take_while(|&code_point| {
-                *code_point == 0xef
-                    || *code_point == 0xbb
-                    || *code_point == 0xbf
-            })
-            .collect::<Vec<_>>()
-            .len();
-
-        let header = unsafe {
-            std::str::from_utf8_unchecked(
-                &memmap[header_start_idx..header_end_idx],
-            )
+        // ⚠️  only a UTF-8 source has a BOM; 0xef 0xbb 0xbf are letters in the legacy encodings
+        let header_start_idx = match encoding {
+            Encoding::Utf8 => memmap
+                .iter()
+                .take_while(|&code_point| {
+                    *code_point == 0xef
+                        || *code_point == 0xbb
+                        || *code_point == 0xbf
+                })
+                .collect::<Vec<_>>()
+                .len(),
+            _ => 0,
         };
 
         // ⚠️  Memory allocation
-        // 🦀 depends on the split delimiter, not yet set
-        let header = header
-            .split(",")
-            .map(|name| name.trim().to_string())
+        // split on the delimiter outside of quotes; the names are unquoted
+        let mut in_quotes = false;
+        let header = memmap[header_start_idx..header_end_idx]
+            .split(|&code_point| {
+                if code_point == dialect.quote {
+                    in_quotes = !in_quotes;
+                }
+                code_point == dialect.delimiter && !in_quotes
+            })
+            .map(|name| match dialect.trim.headers() {
+                true => encoding
+                    .decode_cow(dialect.unquote(name.trim_ascii()))
+                    .trim()
+                    .to_string(),
+                false => {
+                    encoding.decode_cow(dialect.unquote(name)).into_owned()
+                }
+            })
             .collect::<Vec<String>>();
 
         let field_cnt = header.len() as u32;
 
         Header {
-            header,
             new_line,
             field_cnt,
-            delimiter: 0x2C,
+            dialect,
             record_offset: header_end_idx as u32,
+            encoding,
+            preamble: 0,
+            number_format: NumberFormat::default(),
+            nulls: NullValues::default(),
+            map: HeaderMap::new(&header, NameMatching::exact()),
+            header,
         }
     }
     pub fn field_cnt(&self) -> u32 {
         self.field_cnt
     }
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+    /// The number of bytes before the header (BOM, comments) excluded from the index
+    pub fn preamble(&self) -> usize {
+        self.preamble
+    }
+    pub(crate) fn with_preamble(self, preamble: usize) -> Self {
+        Header { preamble, ..self }
+    }
+    /// How the numbers of the fields are written
+    pub fn number_format(&self) -> NumberFormat {
+        self.number_format
+    }
+    pub fn set_number_format(&mut self, number_format: NumberFormat) {
+        self.number_format = number_format;
+    }
+    /// The tokens that mean "missing"
+    pub fn null_values(&self) -> &NullValues {
+        &self.nulls
+    }
+    pub fn set_null_values(&mut self, nulls: NullValues) {
+        self.nulls = nulls;
+    }
+    /// The precomputed name -> position lookup
+    pub fn map(&self) -> &HeaderMap {
+        &self.map
+    }
+    /// Recompute the name lookup, e.g., to ignore case and whitespace
+    pub fn set_name_matching(&mut self, matching: NameMatching) {
+        self.map = HeaderMap::new(&self.header, matching);
+    }
+    /// name -> position of the field; the error lists the near matches
+    pub fn field_index(&self, name: &str) -> Result<usize, StructureError> {
+        self.map.field_index(name)
+    }
 }
 
 /// Generic boundary in the Tape.index
@@ -300,11 +652,15 @@ pub struct Boundary {
 
 impl TapeCore {
     /// ⬜ Configure whether the Header is included in the memmap and index
-    pub fn create(memmap: Mmap, index: StructureIndex, header: Header) -> Self {
+    pub fn create(
+        bytes: impl Into<DataBytes>,
+        index: StructureIndex,
+        header: Header,
+    ) -> Self {
         TapeCore {
             header,
             index,
-            memmap,
+            bytes: bytes.into(),
             first_record_idx: None,
             record_cnt: None,
             record_jump_size: None,
@@ -326,7 +682,7 @@ impl TapeCore {
 
         let problem = (self.index.len() - 1) % *self.record_jump_size.unwrap();
 
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "trace")]
         {
             println!("-------------------------------------------------");
             println!("🚧 CoreTape properties");
@@ -351,6 +707,12 @@ impl TapeCore {
     }
 }
 
+/// The number of chunks of the parallel scans; one per thread of the machine
+fn threads() -> u8 {
+    std::thread::available_parallelism()
+        .map_or(1, |threads| threads.get().min(u8::MAX as usize) as u8)
+}
+
 /// Divide a task into the desired number of jobs. Each job is specified as a boundary.  The units
 /// used to describe the 'task_size' specify the units used to describe a unit in the boundary.
 /// e.g., task_size = 1000 lines, a boundary specifies the number of lines = end - start.
@@ -367,17 +729,17 @@ impl TapeCore {
 /// assert_eq!(result[0], Boundary { start:0, len: 3 });
 /// assert_eq!(result[1], Boundary { start:3, len: 3 });
 /// assert_eq!(result[2], Boundary { start:6, len: 2 });
-/// assert_eq!(8, result.iter().map(|boundary| boundary.len as i32).sum());
+/// assert_eq!(8, result.iter().map(|boundary| boundary.len as i32).sum::<i32>());
 ///
 /// let result = boundaries(1000, 12).unwrap();
 /// assert_eq!(result[0], Boundary { start:0, len: 84 });
 /// assert_eq!(result[1], Boundary { start:84, len: 84 });
 /// assert_eq!(result[11], Boundary { start:917, len: 83 });
-/// assert_eq!(1000, result.iter().map(|boundary| boundary.len as i32).sum());
+/// assert_eq!(1000, result.iter().map(|boundary| boundary.len as i32).sum::<i32>());
 ///
 /// let result = boundaries(8, 12).unwrap();
 /// assert_eq!(result[0], Boundary { start:0, len: 8 });
-/// assert_eq!(8, result.iter().map(|boundary| boundary.len as i32).sum());
+/// assert_eq!(8, result.iter().map(|boundary| boundary.len as i32).sum::<i32>());
 ///
 /// let result = boundaries(0, 3);
 /// assert!(result.is_none());
@@ -397,12 +759,12 @@ pub fn boundaries(task_size: u32, job_count: u8) -> Option<Vec<Boundary>> {
     // sub-routine
     let boundaries = {
         //
-        #[cfg(debug_assertions)]
+        #[cfg(feature = "trace")]
         {
             println!("Creating a slices of work");
             println!("Job count: {} Task size: {}", job_count, task_size);
         }
-        let job_size = dbg!(task_size / job_count as u32);
+        let job_size = task_size / job_count as u32;
         let remainder = task_size % job_count as u32;
 
         let mut boundaries = Vec::with_capacity(job_count as usize);
@@ -421,7 +783,7 @@ pub fn boundaries(task_size: u32, job_count: u8) -> Option<Vec<Boundary>> {
         }
         boundaries
     };
-    #[cfg(debug_assertions)]
+    #[cfg(feature = "trace")]
     println!("Slices of work:\n{:?}", boundaries);
 
     Some(boundaries)
@@ -0,0 +1,456 @@
+///
+/// Dates, year-months and timestamps
+///
+/// A small calendar (proleptic Gregorian; no time zones) to parse the temporal fields.  The
+/// values are ordered, so a range of dates can filter the records of a Tape.
+///
+/// The format strings use the `strftime` specifiers:
+///
+///   %Y  year (4 digits)     %H  hour (1-2 digits)
+///   %m  month (1-2 digits)  %M  minute (1-2 digits)
+///   %d  day (1-2 digits)    %S  second (1-2 digits)
+///   %.f optional fraction of a second (1-9 digits)
+///   %%  a literal '%'
+///
+/// The month and the day default to 1, the time to midnight.
+///
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+use serde::{Serialize, Serializer};
+
+use crate::error::StructureError;
+use crate::typed::FromField;
+
+/// A day of the calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year: i32,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    /// None when the day does not exist
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Date> {
+        if (1..=12).contains(&month)
+            && (1..=days_in_month(year, month)).contains(&day)
+        {
+            Some(Date { year, month, day })
+        } else {
+            None
+        }
+    }
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+    pub fn year_month(&self) -> YearMonth {
+        YearMonth {
+            year: self.year,
+            month: self.month,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A month of the calendar (e.g. the `Year-Month` field)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct YearMonth {
+    year: i32,
+    month: u8,
+}
+
+impl YearMonth {
+    /// None when the month does not exist
+    pub fn new(year: i32, month: u8) -> Option<YearMonth> {
+        (1..=12)
+            .contains(&month)
+            .then_some(YearMonth { year, month })
+    }
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+    /// The first day of the month
+    pub fn first_day(&self) -> Date {
+        Date {
+            year: self.year,
+            month: self.month,
+            day: 1,
+        }
+    }
+}
+
+impl fmt::Display for YearMonth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+/// A date and a time of day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    date: Date,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    nanosecond: u32,
+}
+
+impl DateTime {
+    /// None when the time does not exist
+    pub fn new(
+        date: Date,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        nanosecond: u32,
+    ) -> Option<DateTime> {
+        (hour < 24 && minute < 60 && second < 60 && nanosecond < 1_000_000_000)
+            .then_some(DateTime {
+                date,
+                hour,
+                minute,
+                second,
+                nanosecond,
+            })
+    }
+    pub fn date(&self) -> Date {
+        self.date
+    }
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+    pub fn nanosecond(&self) -> u32 {
+        self.nanosecond
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}",
+            self.date, self.hour, self.minute, self.second
+        )?;
+        if self.nanosecond > 0 {
+            write!(f, ".{:09}", self.nanosecond)?;
+        }
+        Ok(())
+    }
+}
+
+/// A validated format string (see the module documentation for the specifiers)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateFormat<'a>(&'a str);
+
+impl DateFormat<'static> {
+    /// 2016-09-30
+    pub const ISO_DATE: DateFormat<'static> = DateFormat("%Y-%m-%d");
+    /// 2016-09
+    pub const YEAR_MONTH: DateFormat<'static> = DateFormat("%Y-%m");
+    /// 09/30/2016
+    pub const US_DATE: DateFormat<'static> = DateFormat("%m/%d/%Y");
+    /// 2016-09-30T13:45:00.250
+    pub const ISO_DATETIME: DateFormat<'static> =
+        DateFormat("%Y-%m-%dT%H:%M:%S%.f");
+}
+
+impl<'a> DateFormat<'a> {
+    /// InvalidOption when a specifier is unknown, or the year is missing
+    pub fn new(format: &'a str) -> Result<DateFormat<'a>, StructureError> {
+        let invalid = |reason: String| StructureError::InvalidOption {
+            option: "format",
+            reason,
+        };
+        let mut specs = format.split('%').skip(1);
+        let mut has_year = false;
+        while let Some(spec) = specs.next() {
+            match spec.chars().next() {
+                Some('Y') => has_year = true,
+                Some('m' | 'd' | 'H' | 'M' | 'S') => (),
+                Some('.') if spec[1..].starts_with('f') => (),
+                // "%%": the next piece is a literal
+                None if specs.next().is_some() => (),
+                _ => {
+                    return Err(invalid(format!(
+                        "unknown specifier %{} in {:?}",
+                        spec, format
+                    )))
+                }
+            }
+        }
+        if !has_year {
+            return Err(invalid(format!("no %Y in {:?}", format)));
+        }
+        Ok(DateFormat(format))
+    }
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+    /// The time of day is ignored
+    pub fn parse_date(&self, value: &[u8]) -> Option<Date> {
+        self.parse_datetime(value).map(|datetime| datetime.date)
+    }
+    pub fn parse_year_month(&self, value: &[u8]) -> Option<YearMonth> {
+        self.parse_date(value).map(|date| date.year_month())
+    }
+    pub fn parse_datetime(&self, value: &[u8]) -> Option<DateTime> {
+        let parts = Parts::parse(value, self.0.as_bytes())?;
+        let date = Date::new(parts.year?, parts.month, parts.day)?;
+        DateTime::new(
+            date,
+            parts.hour,
+            parts.minute,
+            parts.second,
+            parts.nanosecond,
+        )
+    }
+}
+
+/// The components read by a format
+struct Parts {
+    year: Option<i32>,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    nanosecond: u32,
+}
+
+impl Parts {
+    fn parse(value: &[u8], format: &[u8]) -> Option<Parts> {
+        let mut parts = Parts {
+            year: None,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            nanosecond: 0,
+        };
+        let mut value = value;
+        let mut format = format;
+        while let Some((&byte, rest)) = format.split_first() {
+            format = rest;
+            if byte != b'%' {
+                value = value.strip_prefix(&[byte])?;
+                continue;
+            }
+            let (&spec, rest) = format.split_first()?;
+            format = rest;
+            match spec {
+                b'Y' => parts.year = Some(digits(&mut value, 4, 4)? as i32),
+                b'm' => parts.month = digits(&mut value, 1, 2)? as u8,
+                b'd' => parts.day = digits(&mut value, 1, 2)? as u8,
+                b'H' => parts.hour = digits(&mut value, 1, 2)? as u8,
+                b'M' => parts.minute = digits(&mut value, 1, 2)? as u8,
+                b'S' => parts.second = digits(&mut value, 1, 2)? as u8,
+                b'.' => {
+                    format = format.strip_prefix(b"f")?;
+                    if let Some(rest) = value.strip_prefix(b".") {
+                        value = rest;
+                        let len = value.len();
+                        let fraction = digits(&mut value, 1, 9)?;
+                        let scale = 10_u32.pow(9 - (len - value.len()) as u32);
+                        parts.nanosecond = fraction * scale;
+                    }
+                }
+                b'%' => value = value.strip_prefix(b"%")?,
+                _ => return None,
+            }
+        }
+        value.is_empty().then_some(parts)
+    }
+}
+
+/// Consume min..=max leading digits
+fn digits(value: &mut &[u8], min: usize, max: usize) -> Option<u32> {
+    let len = value
+        .iter()
+        .take(max)
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    if len < min {
+        return None;
+    }
+    let (digits, rest) = value.split_at(len);
+    *value = rest;
+    Some(
+        digits
+            .iter()
+            .fold(0, |acc, &byte| acc * 10 + (byte - b'0') as u32),
+    )
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// ISO (2016-09-30), then US (09/30/2016)
+impl FromField for Date {
+    const KIND: &'static str = "date";
+    fn from_field(value: &[u8]) -> Option<Self> {
+        DateFormat::ISO_DATE
+            .parse_date(value)
+            .or_else(|| DateFormat::US_DATE.parse_date(value))
+    }
+}
+/// 2016-09
+impl FromField for YearMonth {
+    const KIND: &'static str = "year-month";
+    fn from_field(value: &[u8]) -> Option<Self> {
+        DateFormat::YEAR_MONTH.parse_year_month(value)
+    }
+}
+/// ISO; 'T' or ' ' between the date and the time, an optional trailing 'Z'
+impl FromField for DateTime {
+    const KIND: &'static str = "datetime";
+    fn from_field(value: &[u8]) -> Option<Self> {
+        let value = value.strip_suffix(b"Z").unwrap_or(value);
+        DateFormat::ISO_DATETIME.parse_datetime(value).or_else(|| {
+            DateFormat("%Y-%m-%d %H:%M:%S%.f").parse_datetime(value)
+        })
+    }
+}
+
+/// serde: the text of the value (see `FromField`)
+macro_rules! serde_text {
+    ($($ty:ident),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<Self, D::Error> {
+                    struct Text;
+                    impl<'de> Visitor<'de> for Text {
+                        type Value = $ty;
+
+                        fn expecting(
+                            &self,
+                            f: &mut fmt::Formatter,
+                        ) -> fmt::Result {
+                            write!(f, "a {}", <$ty as FromField>::KIND)
+                        }
+                        fn visit_str<E: de::Error>(
+                            self,
+                            value: &str,
+                        ) -> Result<$ty, E> {
+                            $ty::from_field(value.trim().as_bytes()).ok_or_else(
+                                || E::invalid_value(Unexpected::Str(value), &self),
+                            )
+                        }
+                    }
+                    deserializer.deserialize_str(Text)
+                }
+            }
+        )*
+    };
+}
+serde_text!(Date, YearMonth, DateTime);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create, Encoding};
+
+    #[test]
+    fn formats() {
+        let date = Date::new(2016, 9, 30).unwrap();
+        assert_eq!(Date::from_field(b"2016-09-30"), Some(date));
+        assert_eq!(Date::from_field(b"09/30/2016"), Some(date));
+        assert_eq!(Date::from_field(b"9/30/2016"), Some(date));
+        assert_eq!(Date::from_field(b"2016-02-30"), None);
+        assert_eq!(Date::from_field(b"2016-02-29"), Date::new(2016, 2, 29));
+        assert_eq!(Date::from_field(b"2016-09-30x"), None);
+        assert_eq!(YearMonth::from_field(b"2016-13"), None);
+
+        let datetime =
+            DateTime::from_field(b"2016-09-30 13:45:07.25Z").unwrap();
+        assert_eq!(datetime.date(), date);
+        assert_eq!(datetime.nanosecond(), 250_000_000);
+        assert_eq!(datetime.to_string(), "2016-09-30T13:45:07.250000000");
+        assert_eq!(DateTime::from_field(b"2016-09-30T24:00:00"), None);
+
+        let format = DateFormat::new("%d.%m.%Y %H%%").unwrap();
+        assert_eq!(
+            format.parse_datetime(b"30.09.2016 13%").unwrap().hour(),
+            13
+        );
+        assert!(DateFormat::new("%d/%m").is_err());
+        assert!(DateFormat::new("%Y %q").is_err());
+    }
+    #[test]
+    fn temporal_fields() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let months = tape.column("Year-Month").unwrap();
+        let first = months.get_year_month(0).unwrap().unwrap();
+        assert_eq!(first, YearMonth::new(2016, 9).unwrap());
+        assert_eq!(first.to_string(), "2016-09");
+        let mut values = Vec::new();
+        months.parse_into::<YearMonth>(&mut values).unwrap();
+        let earliest = values.iter().flatten().min().unwrap();
+        assert_eq!(*earliest, YearMonth::new(2015, 3).unwrap());
+
+        let record = tape.as_records().nth(2).unwrap();
+        let format = DateFormat::new("%Y-%m").unwrap();
+        assert_eq!(
+            record.get_date_with(6, &format).unwrap(),
+            Date::new(2015, 3, 1)
+        );
+        assert!(matches!(
+            record.get_date(6),
+            Err(StructureError::InvalidValue {
+                record: 2,
+                field: 6,
+                kind: "date",
+                ..
+            })
+        ));
+    }
+    #[test]
+    fn serde() {
+        let date: Date = serde_json::from_str("\"09/30/2016\"").unwrap();
+        assert_eq!(serde_json::to_string(&date).unwrap(), "\"2016-09-30\"");
+        let month: YearMonth = serde_json::from_str("\"2016-09\"").unwrap();
+        assert_eq!(month, YearMonth::new(2016, 9).unwrap());
+        assert!(serde_json::from_str::<DateTime>("\"2016-09\"").is_err());
+    }
+}
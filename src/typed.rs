@@ -0,0 +1,277 @@
+///
+/// Typed field values
+///
+/// The value of a field (quotes removed, escapes resolved, spaces trimmed) parsed to a number
+/// or a boolean.  An empty value is `None`; a value that does not parse is an error that
+/// hosts the position of the field.
+///
+/// 🔑 Runs of 8 to 16 digits (ids, counts...) are parsed 16 bytes at a time:
+///
+///    digits - '0'  ->  pairs (x10 + x1)  ->  quads (x100 + x1)  ->  octets (x10_000 + x1)
+///
+/// The digits are ASCII in all of the supported encodings; there is no need to transcode.
+///
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use crate::builder::simd_available;
+use crate::dialect::Dialect;
+use crate::encoding::Encoding;
+use crate::error::StructureError;
+use crate::number_format::NumberFormat;
+
+/// A type that can be parsed from the value of a field
+pub trait FromField: Sized {
+    /// The name of the type in the error messages
+    const KIND: &'static str;
+    /// trimmed, non-empty value -> Self
+    fn from_field(value: &[u8]) -> Option<Self>;
+    /// The value written with a number format; the format is ignored by default
+    fn from_formatted(value: &[u8], format: &NumberFormat) -> Option<Self> {
+        let _ = format;
+        Self::from_field(value)
+    }
+}
+
+impl FromField for u64 {
+    const KIND: &'static str = "u64";
+    fn from_field(value: &[u8]) -> Option<Self> {
+        parse_u64(value.strip_prefix(b"+").unwrap_or(value))
+    }
+    fn from_formatted(value: &[u8], format: &NumberFormat) -> Option<Self> {
+        from_integer(value, format)
+    }
+}
+impl FromField for i64 {
+    const KIND: &'static str = "i64";
+    fn from_field(value: &[u8]) -> Option<Self> {
+        match value.split_first()? {
+            (b'-', digits) => {
+                let magnitude = parse_u64(digits)?;
+                if magnitude == i64::MIN.unsigned_abs() {
+                    Some(i64::MIN)
+                } else {
+                    i64::try_from(magnitude).ok().map(|n| -n)
+                }
+            }
+            (b'+', digits) => i64::try_from(parse_u64(digits)?).ok(),
+            _ => i64::try_from(parse_u64(value)?).ok(),
+        }
+    }
+    fn from_formatted(value: &[u8], format: &NumberFormat) -> Option<Self> {
+        from_integer(value, format)
+    }
+}
+impl FromField for f64 {
+    const KIND: &'static str = "f64";
+    fn from_field(value: &[u8]) -> Option<Self> {
+        std::str::from_utf8(value).ok()?.parse().ok()
+    }
+    fn from_formatted(value: &[u8], format: &NumberFormat) -> Option<Self> {
+        if format.is_plain() {
+            return Self::from_field(value);
+        }
+        let plain = format.plain(value)?;
+        let number = Self::from_field(&plain.text)?;
+        Some(if plain.percent {
+            number / 100.0
+        } else {
+            number
+        })
+    }
+}
+
+/// ⚠️  An integer percent is invalid
+fn from_integer<T: FromField>(
+    value: &[u8],
+    format: &NumberFormat,
+) -> Option<T> {
+    if format.is_plain() {
+        return T::from_field(value);
+    }
+    let plain = format.plain(value)?;
+    match plain.percent {
+        true => None,
+        false => T::from_field(&plain.text),
+    }
+}
+impl FromField for bool {
+    const KIND: &'static str = "bool";
+    fn from_field(value: &[u8]) -> Option<Self> {
+        const TRUE: [&[u8]; 5] = [b"true", b"t", b"yes", b"y", b"1"];
+        const FALSE: [&[u8]; 5] = [b"false", b"f", b"no", b"n", b"0"];
+        let is = |words: &[&[u8]]| {
+            words.iter().any(|word| word.eq_ignore_ascii_case(value))
+        };
+        if is(&TRUE) {
+            Some(true)
+        } else if is(&FALSE) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// raw field -> value; the position of the field is reported on error.
+pub(crate) fn parse_field_with<T>(
+    raw: &[u8],
+    (dialect, encoding): (&Dialect, Encoding),
+    (record, field): (u32, u32),
+    kind: &'static str,
+    parse: impl FnOnce(&[u8]) -> Option<T>,
+) -> Result<Option<T>, StructureError> {
+    let value = dialect.unescape(raw);
+    // 🔑 the symbols (e.g. '€') are compared in UTF-8
+    let value = match encoding.is_single_byte() && !value.is_ascii() {
+        true => {
+            Cow::Owned(encoding.decode_cow(value).into_owned().into_bytes())
+        }
+        false => value,
+    };
+    let trimmed = value.trim_ascii();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    parse(trimmed)
+        .map(Some)
+        .ok_or_else(|| StructureError::InvalidValue {
+            record,
+            field,
+            kind,
+            value: String::from_utf8_lossy(raw).into_owned(),
+        })
+}
+
+/// A run of ASCII digits -> u64; None when empty, not a digit, or on overflow.
+pub fn parse_u64(digits: &[u8]) -> Option<u64> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if (8..=16).contains(&digits.len()) && simd_available() {
+            // ⚠️  Safety: the instructions are available
+            return unsafe { parse_digits_simd(digits) };
+        }
+    }
+    parse_digits(digits)
+}
+
+/// byte-at-a-time reference
+fn parse_digits(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0_u64, |acc, &byte| {
+        if !byte.is_ascii_digit() {
+            return None;
+        }
+        acc.checked_mul(10)?.checked_add((byte - b'0') as u64)
+    })
+}
+
+/// 8 to 16 digits; left-padded with '0' to a 16 byte register.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3,sse4.1")]
+unsafe fn parse_digits_simd(digits: &[u8]) -> Option<u64> {
+    let mut padded = [b'0'; 16];
+    padded[16 - digits.len()..].copy_from_slice(digits);
+    let chunk = _mm_loadu_si128(padded.as_ptr() as *const __m128i);
+    let values = _mm_sub_epi8(chunk, _mm_set1_epi8(b'0' as i8));
+    // signed: the bytes below '0' wrap to negative values
+    let invalid = _mm_or_si128(
+        _mm_cmplt_epi8(values, _mm_setzero_si128()),
+        _mm_cmpgt_epi8(values, _mm_set1_epi8(9)),
+    );
+    if _mm_movemask_epi8(invalid) != 0 {
+        return None;
+    }
+    let pairs = _mm_maddubs_epi16(
+        values,
+        _mm_setr_epi8(10, 1, 10, 1, 10, 1, 10, 1, 10, 1, 10, 1, 10, 1, 10, 1),
+    );
+    let quads =
+        _mm_madd_epi16(pairs, _mm_setr_epi16(100, 1, 100, 1, 100, 1, 100, 1));
+    let quads = _mm_packus_epi32(quads, quads);
+    let octets = _mm_madd_epi16(
+        quads,
+        _mm_setr_epi16(10_000, 1, 10_000, 1, 10_000, 1, 10_000, 1),
+    );
+    let high = _mm_cvtsi128_si32(octets) as u64;
+    let low = _mm_extract_epi32(octets, 1) as u64;
+    Some(high * 100_000_000 + low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create, Encoding};
+
+    #[test]
+    fn digits() {
+        for digits in [
+            &b"0"[..],
+            b"1003002819",
+            b"12345678",
+            b"9999999999999999",
+            b"0000000000000001",
+            b"18446744073709551615",
+        ] {
+            let expected = std::str::from_utf8(digits).unwrap().parse().ok();
+            assert_eq!(parse_u64(digits), expected);
+            assert_eq!(parse_digits(digits), expected);
+        }
+        assert_eq!(parse_u64(b"18446744073709551616"), None);
+        assert_eq!(parse_u64(b"1234/678"), None);
+        assert_eq!(parse_u64(b"12345:78"), None);
+        assert_eq!(parse_u64(b"123456\xb578"), None);
+        assert_eq!(i64::from_field(b"-9223372036854775808"), Some(i64::MIN));
+        assert_eq!(i64::from_field(b"9223372036854775808"), None);
+        assert_eq!(i64::from_field(b"+42"), Some(42));
+        assert_eq!(bool::from_field(b"Yes"), Some(true));
+        assert_eq!(f64::from_field(b"1.5e3"), Some(1500.0));
+    }
+    #[test]
+    fn typed_fields() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let record = tape.as_records().nth(6).unwrap();
+        assert_eq!(record.get_u64(0).unwrap(), Some(1003002819));
+        assert_eq!(record.get_i64(7).unwrap(), Some(1));
+        assert_eq!(record.get_f64(7).unwrap(), Some(1.0));
+        assert_eq!(record.get_i64(8).unwrap(), None);
+        assert!(matches!(
+            record.get_i64(1),
+            Err(StructureError::InvalidValue {
+                record: 6,
+                field: 1,
+                kind: "i64",
+                ..
+            })
+        ));
+
+        let counts = tape.column("NRx Count").unwrap();
+        assert_eq!(counts.get_u64(0).unwrap(), Some(2));
+        let mut values = vec![Some(-1)];
+        counts.parse_i64_into(&mut values).unwrap();
+        assert_eq!(
+            values,
+            [2, 1, 2, 2, 2, 1, 1]
+                .iter()
+                .map(|&n| Some(n))
+                .collect::<Vec<_>>()
+        );
+        let names = tape.column(1_usize).unwrap().slice(3..5).unwrap();
+        assert!(matches!(
+            names.parse_i64_into(&mut values),
+            Err(StructureError::InvalidValue {
+                record: 3,
+                field: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            tape.column(1_usize).unwrap().get_bool(0),
+            Err(StructureError::InvalidValue { kind: "bool", .. })
+        ));
+    }
+}
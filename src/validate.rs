@@ -0,0 +1,458 @@
+///
+/// Contract checks of a Tape against a declared Schema
+///
+/// The header is compared with the columns of the Schema (names and order); then each value
+/// of a declared column is checked, in this order:
+///
+///   null (not nullable) -> type -> allowed values -> pattern -> min/max
+///
+/// The first failed check is the violation of the value.  The records are split in chunks (see
+/// `Tape::chunks`) and the chunks are checked in parallel; the reports are merged in the order
+/// of the records.
+///
+/// ⚠️  The first `MAX_VIOLATIONS` of each column are listed; all of them are counted.
+///
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use serde::Serialize;
+
+use crate::error::StructureError;
+use crate::records::Record;
+use crate::schema::{Bound, ColumnSchema, ColumnType, Schema};
+use crate::tape::Tape;
+use crate::temporal::{Date, DateTime, YearMonth};
+use crate::typed::FromField;
+
+/// The number of violations listed per column
+pub const MAX_VIOLATIONS: usize = 100;
+
+/// What was found
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct ValidationReport {
+    /// The number of records checked
+    pub records: u32,
+    pub header: Vec<HeaderViolation>,
+    /// The declared columns found in the header, in the order of the Schema
+    pub columns: Vec<ColumnReport>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.header.is_empty() && self.violation_cnt() == 0
+    }
+    /// The number of values that failed a check
+    pub fn violation_cnt(&self) -> u64 {
+        self.columns
+            .iter()
+            .map(|column| column.violation_cnt as u64)
+            .sum()
+    }
+    pub fn get(&self, name: &str) -> Option<&ColumnReport> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+/// A difference of the header and the Schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderViolation {
+    /// Declared, not in the header
+    Missing { name: String },
+    /// In the header, not declared
+    Unexpected { name: String, field: usize },
+    /// Not at the declared position
+    Moved {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// The violations of a column
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct ColumnReport {
+    pub name: String,
+    pub violation_cnt: u32,
+    /// The first MAX_VIOLATIONS, in the order of the records
+    pub violations: Vec<Violation>,
+}
+
+impl ColumnReport {
+    fn push(&mut self, record: u32, kind: ViolationKind, value: &str) {
+        self.violation_cnt += 1;
+        if self.violations.len() < MAX_VIOLATIONS {
+            self.violations.push(Violation {
+                record,
+                kind,
+                value: value.to_string(),
+            });
+        }
+    }
+    fn merge(&mut self, other: ColumnReport) {
+        self.violation_cnt += other.violation_cnt;
+        let room = MAX_VIOLATIONS - self.violations.len();
+        self.violations
+            .extend(other.violations.into_iter().take(room));
+    }
+}
+
+/// A value that failed a check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// excludes the header
+    pub record: u32,
+    pub kind: ViolationKind,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// Null or missing, and the column is not nullable
+    Null,
+    /// Does not parse to the type of the column
+    Type,
+    NotAllowed,
+    Pattern,
+    BelowMin,
+    AboveMax,
+}
+
+/// A typed value; compared within a variant
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum Value<'a> {
+    Number(f64),
+    Boolean(bool),
+    Date(Date),
+    YearMonth(YearMonth),
+    DateTime(DateTime),
+    Text(Cow<'a, str>),
+}
+
+/// A declared column and its position in the Tape
+struct Check<'a> {
+    field_idx: usize,
+    column: &'a ColumnSchema,
+    min: Option<Value<'static>>,
+    max: Option<Value<'static>>,
+}
+
+impl<'a> Check<'a> {
+    fn new(
+        field_idx: usize,
+        column: &'a ColumnSchema,
+    ) -> Result<Check<'a>, StructureError> {
+        let limit = |option: &'static str, bound: &Option<Bound>| {
+            bound
+                .as_ref()
+                .map(|bound| limit(column.column_type, bound))
+                .transpose()
+                .map_err(|reason| StructureError::InvalidOption {
+                    option,
+                    reason: format!(
+                        "{} of {:?}: {}",
+                        option, column.name, reason
+                    ),
+                })
+        };
+        Ok(Check {
+            field_idx,
+            column,
+            min: limit("min", &column.min)?,
+            max: limit("max", &column.max)?,
+        })
+    }
+    fn check(&self, record: &Record, report: &mut ColumnReport) {
+        let idx = record.idx();
+        let text = match record.get(self.field_idx) {
+            Some(_) if !record.is_null(self.field_idx) => {
+                record.value(self.field_idx).unwrap_or_default()
+            }
+            raw => {
+                if !self.column.nullable {
+                    let raw = raw.map(String::from_utf8_lossy);
+                    report.push(
+                        idx,
+                        ViolationKind::Null,
+                        &raw.unwrap_or_default(),
+                    );
+                }
+                return;
+            }
+        };
+        let text = text.trim();
+        let value = match typed(
+            record,
+            self.field_idx,
+            self.column.column_type,
+            text,
+        ) {
+            Some(value) => value,
+            None => return report.push(idx, ViolationKind::Type, text),
+        };
+        let violation =
+            if self.column.values.as_ref().is_some_and(|values| {
+                !values.iter().any(|allowed| allowed == text)
+            }) {
+                Some(ViolationKind::NotAllowed)
+            } else if self
+                .column
+                .pattern
+                .as_ref()
+                .is_some_and(|pattern| !pattern.is_match(text))
+            {
+                Some(ViolationKind::Pattern)
+            } else if self.min.as_ref().is_some_and(|min| {
+                value.partial_cmp(min) == Some(Ordering::Less)
+            }) {
+                Some(ViolationKind::BelowMin)
+            } else if self.max.as_ref().is_some_and(|max| {
+                value.partial_cmp(max) == Some(Ordering::Greater)
+            }) {
+                Some(ViolationKind::AboveMax)
+            } else {
+                None
+            };
+        if let Some(kind) = violation {
+            report.push(idx, kind, text);
+        }
+    }
+}
+
+/// The value as the type of the column (see the typed accessors of the Record)
+fn typed<'a>(
+    record: &Record,
+    field_idx: usize,
+    column_type: ColumnType,
+    text: &'a str,
+) -> Option<Value<'a>> {
+    match column_type {
+        ColumnType::Integer => record
+            .get_i64(field_idx)
+            .ok()?
+            .map(|v| Value::Number(v as f64)),
+        ColumnType::Float => record.get_f64(field_idx).ok()?.map(Value::Number),
+        ColumnType::Boolean => {
+            record.get_bool(field_idx).ok()?.map(Value::Boolean)
+        }
+        ColumnType::Date => record.get_date(field_idx).ok()?.map(Value::Date),
+        ColumnType::YearMonth => {
+            record.get_year_month(field_idx).ok()?.map(Value::YearMonth)
+        }
+        ColumnType::DateTime => {
+            record.get_datetime(field_idx).ok()?.map(Value::DateTime)
+        }
+        ColumnType::Categorical | ColumnType::Text => {
+            Some(Value::Text(Cow::Borrowed(text)))
+        }
+    }
+}
+
+/// The bound as the type of the column
+fn limit(
+    column_type: ColumnType,
+    bound: &Bound,
+) -> Result<Value<'static>, String> {
+    let text = match bound {
+        Bound::Number(number) => match column_type {
+            ColumnType::Integer | ColumnType::Float => {
+                return Ok(Value::Number(*number))
+            }
+            _ => number.to_string(),
+        },
+        Bound::Text(text) => text.clone(),
+    };
+    let bytes = text.as_bytes();
+    let value = match column_type {
+        ColumnType::Integer | ColumnType::Float => {
+            f64::from_field(bytes).map(Value::Number)
+        }
+        ColumnType::Boolean => None,
+        ColumnType::Date => Date::from_field(bytes).map(Value::Date),
+        ColumnType::YearMonth => {
+            YearMonth::from_field(bytes).map(Value::YearMonth)
+        }
+        ColumnType::DateTime => {
+            DateTime::from_field(bytes).map(Value::DateTime)
+        }
+        ColumnType::Categorical | ColumnType::Text => {
+            Some(Value::Text(Cow::Owned(text.clone())))
+        }
+    };
+    value.ok_or_else(|| {
+        format!("{:?} is not a {} bound", text, column_type.name())
+    })
+}
+
+/// Check the Tape; the records are split in `chunks`
+pub(crate) fn validate(
+    tape: &Tape,
+    schema: &Schema,
+    chunks: u8,
+) -> Result<ValidationReport, StructureError> {
+    let mut report = ValidationReport::default();
+    let mut checks = Vec::new();
+    for (expected, column) in schema.columns.iter().enumerate() {
+        match tape.field_index(&column.name) {
+            Ok(found) => {
+                if found != expected {
+                    report.header.push(HeaderViolation::Moved {
+                        name: column.name.clone(),
+                        expected,
+                        found,
+                    });
+                }
+                checks.push(Check::new(found, column)?);
+            }
+            Err(_) => report.header.push(HeaderViolation::Missing {
+                name: column.name.clone(),
+            }),
+        }
+    }
+    for (field, name) in tape.header().iter().enumerate() {
+        if checks.iter().all(|check| check.field_idx != field) {
+            report.header.push(HeaderViolation::Unexpected {
+                name: name.clone(),
+                field,
+            });
+        }
+    }
+
+    report.records = tape.as_records().len() as u32;
+    let ranges = tape.record_ranges(chunks)?;
+    let parts = std::thread::scope(|scope| {
+        let checks = &checks;
+        ranges
+            .into_iter()
+            .map(|(first, record_cnt)| {
+                scope.spawn(move || scan(tape, checks, first, record_cnt))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().expect("a validation thread"))
+            .collect::<Vec<_>>()
+    });
+    report.columns = checks
+        .iter()
+        .map(|check| ColumnReport {
+            name: check.column.name.clone(),
+            ..ColumnReport::default()
+        })
+        .collect();
+    for part in parts {
+        for (column, other) in report.columns.iter_mut().zip(part) {
+            column.merge(other);
+        }
+    }
+    Ok(report)
+}
+
+/// The violations of a range of the records
+fn scan(
+    tape: &Tape,
+    checks: &[Check],
+    first: usize,
+    record_cnt: usize,
+) -> Vec<ColumnReport> {
+    let mut reports = vec![ColumnReport::default(); checks.len()];
+    for record in tape.as_records().skip(first).take(record_cnt) {
+        for (check, report) in checks.iter().zip(reports.iter_mut()) {
+            check.check(&record, report);
+        }
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create, Encoding};
+
+    const CONTRACT: &str = "NPI Number: integer\n\
+                            \tpattern: 10 digits\n\
+                            SPI Number: text\n\
+                            \tpattern: S9{1,9}\n\
+                            Primary Specialty Desc: text\n\
+                            Practitioner State: categorical\n\
+                            \tvalues: FL | NY\n\
+                            Practitioner Zip Code: integer\n\
+                            \tmin: 10000\n\
+                            Payment Type Group: categorical\n\
+                            Year-Month: yearmonth\n\
+                            \tmin: 2016-01\n\
+                            NRx Count: integer\n\
+                            \tmax: 1\n";
+
+    #[test]
+    fn sample_rx() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let schema = Schema::from_text(CONTRACT).unwrap();
+        let report = validate(&tape, &schema, 3).unwrap();
+        assert_eq!(report.records, 7);
+        assert!(report.header.is_empty());
+        assert!(!report.is_valid());
+
+        let npi = report.get("NPI Number").unwrap();
+        assert_eq!(npi.violations.len(), 1);
+        assert_eq!(npi.violations[0].record, 4);
+        assert_eq!(npi.violations[0].kind, ViolationKind::Pattern);
+        let state = report.get("Practitioner State").unwrap();
+        assert_eq!(state.violations[0].value, "F");
+        assert_eq!(state.violations[0].kind, ViolationKind::NotAllowed);
+        let zip = report.get("Practitioner Zip Code").unwrap();
+        assert_eq!(zip.violations[0].kind, ViolationKind::BelowMin);
+        let month = report.get("Year-Month").unwrap();
+        assert_eq!(month.violations[0].record, 2);
+        let nrx = report.get("NRx Count").unwrap();
+        let records =
+            nrx.violations.iter().map(|v| v.record).collect::<Vec<_>>();
+        assert_eq!(records, vec![0, 2, 3, 4]);
+        assert_eq!(report.violation_cnt(), 8);
+
+        // the same report from one chunk
+        assert_eq!(validate(&tape, &schema, 1).unwrap(), report);
+        assert_eq!(tape.validate(&schema).unwrap(), report);
+    }
+    #[test]
+    fn header_and_nulls() {
+        let data = b"id,name,extra\n1,a,x\n,b,y\nx,,z\n";
+        let tape = crate::TapeBuilder::new()
+            .build_from_bytes(&data[..])
+            .unwrap();
+        let schema =
+            Schema::from_text("name: text nullable\nid: integer\ncity: text\n")
+                .unwrap();
+        let report = validate(&tape, &schema, 2).unwrap();
+        assert_eq!(
+            report.header,
+            vec![
+                HeaderViolation::Moved {
+                    name: "name".into(),
+                    expected: 0,
+                    found: 1
+                },
+                HeaderViolation::Moved {
+                    name: "id".into(),
+                    expected: 1,
+                    found: 0
+                },
+                HeaderViolation::Missing {
+                    name: "city".into()
+                },
+                HeaderViolation::Unexpected {
+                    name: "extra".into(),
+                    field: 2
+                },
+            ]
+        );
+        let id = report.get("id").unwrap();
+        let kinds = id.violations.iter().map(|v| v.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![ViolationKind::Null, ViolationKind::Type]);
+        assert_eq!(report.get("name").unwrap().violation_cnt, 0);
+
+        let mut bad = Schema::from_text("id: boolean\n").unwrap();
+        bad.columns[0].min = Some(Bound::Number(0.0));
+        assert!(validate(&tape, &bad, 1).is_err());
+    }
+}
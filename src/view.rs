@@ -0,0 +1,414 @@
+///
+/// Virtual tapes: a range of the records, and a projection of the fields
+///
+/// A view shares the bytes and the index of the Tape (a cheap clone); nothing is copied.  The
+/// records are renumbered from the start of the range, and the fields from the start of the
+/// projection.
+///
+/// 🔑 A range of the records (`TapeView`) implements `RecordSource`; the lookup code works the
+///    same way on a view.
+///
+/// ⚠️  A `Projection` is not a `RecordSource`: the fields of its records are not contiguous in
+///    the data.  It has the field lookups, and a record is a copy of its fields.
+///
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::column::ColumnKey;
+use crate::dialect::Dialect;
+use crate::encoding::Encoding;
+use crate::error::StructureError;
+use crate::header_map::{HeaderMap, NameMatching};
+use crate::record_source::RecordSource;
+use crate::stage1::{KeyToPos, NewLine, StructureIndex};
+use crate::tape::Tape;
+
+/// A range of the records of a Tape
+#[derive(Debug, Clone)]
+pub struct TapeView {
+    tape: Tape,
+    /// records [start, end) of the Tape; excludes the header
+    records: Range<u32>,
+}
+
+impl TapeView {
+    pub(crate) fn new(tape: &Tape) -> Self {
+        TapeView {
+            tape: tape.clone(),
+            records: 0..tape.record_cnt.saturating_sub(1),
+        }
+    }
+    /// The number of records in view
+    pub fn len(&self) -> usize {
+        (self.records.end - self.records.start) as usize
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The position of the first record of the view in the Tape
+    pub fn first_record(&self) -> u32 {
+        self.records.start
+    }
+    /// The names of the fields
+    pub fn header(&self) -> Vec<&str> {
+        self.tape.header().iter().map(String::as_str).collect()
+    }
+    /// name -> position of the field
+    pub fn field_index(&self, name: &str) -> Result<usize, StructureError> {
+        self.tape.field_index(name)
+    }
+    /// A range of the records in this view; None when out of range.
+    pub fn slice(&self, records: Range<u32>) -> Option<TapeView> {
+        if records.start > records.end || records.end as usize > self.len() {
+            return None;
+        }
+        Some(TapeView {
+            tape: self.tape.clone(),
+            records: self.records.start + records.start
+                ..self.records.start + records.end,
+        })
+    }
+    /// Select, and order the fields
+    pub fn project<K: ColumnKey>(
+        &self,
+        keys: &[K],
+    ) -> Result<Projection, StructureError> {
+        let fields = keys
+            .iter()
+            .map(|key| key.field_idx(&self.tape.header))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Projection {
+            view: self.clone(),
+            fields,
+        })
+    }
+    /// view record -> position of the first slot of the record in the index
+    fn idx_start(&self, record_idx: u32) -> usize {
+        (self.records.start + record_idx + 1) as usize
+            * *self.tape.record_jump_size
+    }
+}
+
+impl RecordSource for TapeView {
+    fn record_span(
+        &self,
+        record_idx: u32,
+    ) -> Result<Option<Range<usize>>, StructureError> {
+        match (record_idx as usize) < self.len() {
+            true => (&self.tape).record_span(self.records.start + record_idx),
+            false => Ok(None),
+        }
+    }
+    fn field_span(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<Range<usize>>, StructureError> {
+        match (record_idx as usize) < self.len() {
+            true => (&self.tape)
+                .field_span(self.records.start + record_idx, field_idx),
+            false => Ok(None),
+        }
+    }
+    /// # Safety
+    ///
+    /// As `RecordSource::seek_record_unchecked`; the positions are those of the view.
+    unsafe fn seek_record_unchecked(&self, record_idx: u32) -> &str {
+        let idx_start = self.idx_start(record_idx);
+        let field_cnt = self.tape.header.field_cnt as usize;
+        let mem_start = self.tape.index().get_unchecked(idx_start);
+        let mem_end = self.tape.index().get_unchecked(idx_start + field_cnt);
+
+        std::str::from_utf8_unchecked(
+            self.data_bytes().get_unchecked(mem_start + 1..mem_end),
+        )
+    }
+    /// # Safety
+    ///
+    /// As `RecordSource::seek_field_unchecked`; the positions are those of the view.
+    unsafe fn seek_field_unchecked(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> &str {
+        let idx_start = self.idx_start(record_idx) + field_idx as usize;
+        let mem_start = self.tape.index().get_unchecked(idx_start);
+        let mem_end = self.tape.index().get_unchecked(idx_start + 1);
+
+        std::str::from_utf8_unchecked(
+            self.data_bytes().get_unchecked(mem_start + 1..mem_end),
+        )
+    }
+    /// Includes the header
+    fn record_cnt(&self) -> Option<u32> {
+        Some(self.len() as u32 + 1)
+    }
+    fn index(&self) -> &StructureIndex {
+        self.tape.index()
+    }
+    fn record_jump_size(&self) -> Result<KeyToPos, StructureError> {
+        Ok(self.tape.record_jump_size)
+    }
+    fn field_cnt(&self) -> u32 {
+        self.tape.header.field_cnt
+    }
+    fn new_line_tag(&self) -> &NewLine {
+        &self.tape.header.new_line
+    }
+    fn data_bytes(&self) -> &[u8] {
+        self.tape.bytes()
+    }
+    fn encoding(&self) -> Encoding {
+        self.tape.header.encoding()
+    }
+    fn dialect(&self) -> Dialect {
+        self.tape.header.dialect()
+    }
+}
+
+/// A selection of the fields of a range of the records, in the order of the keys
+#[derive(Debug, Clone)]
+pub struct Projection {
+    view: TapeView,
+    /// projection field -> Tape field
+    fields: Vec<usize>,
+}
+
+impl Projection {
+    /// The number of records in view
+    pub fn len(&self) -> usize {
+        self.view.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.view.is_empty()
+    }
+    /// The position of the first record in the Tape
+    pub fn first_record(&self) -> u32 {
+        self.view.first_record()
+    }
+    /// The records in view, with all of their fields
+    pub fn view(&self) -> &TapeView {
+        &self.view
+    }
+    /// The names of the fields in view
+    pub fn header(&self) -> Vec<&str> {
+        self.fields
+            .iter()
+            .map(|&field| self.view.tape.header()[field].as_str())
+            .collect()
+    }
+    /// The fields of the Tape in view
+    pub fn fields(&self) -> &[usize] {
+        &self.fields
+    }
+    /// The number of fields in view
+    pub fn field_cnt(&self) -> u32 {
+        self.fields.len() as u32
+    }
+    /// name -> position of the field in the projection
+    pub fn field_index(&self, name: &str) -> Result<usize, StructureError> {
+        let field = self.view.tape.field_index(name)?;
+        self.position(field, name)
+    }
+    /// A range of the records in this projection; None when out of range.
+    pub fn slice(&self, records: Range<u32>) -> Option<Projection> {
+        Some(Projection {
+            view: self.view.slice(records)?,
+            fields: self.fields.clone(),
+        })
+    }
+    /// Select, and order the fields; the keys select fields of the Tape that must be in view.
+    pub fn project<K: ColumnKey>(
+        &self,
+        keys: &[K],
+    ) -> Result<Projection, StructureError> {
+        let header = &self.view.tape.header;
+        let fields = keys
+            .iter()
+            .map(|key| {
+                let field = key.field_idx(header)?;
+                self.position(field, &self.view.tape.header()[field])
+                    .map(|position| self.fields[position])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Projection {
+            view: self.view.clone(),
+            fields,
+        })
+    }
+    /// The location of a field in the data bytes (see `RecordSource::field_span`)
+    pub fn field_span(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<Range<usize>>, StructureError> {
+        match self.fields.get(field_idx as usize) {
+            Some(&field) => self.view.field_span(record_idx, field as u32),
+            None => Ok(None),
+        }
+    }
+    /// The raw bytes of a field
+    pub fn seek_field_bytes(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<&[u8]>, StructureError> {
+        match self.fields.get(field_idx as usize) {
+            Some(&field) => {
+                self.view.seek_field_bytes(record_idx, field as u32)
+            }
+            None => Ok(None),
+        }
+    }
+    /// The field validated as UTF-8 (see `RecordSource::seek_field`)
+    pub fn seek_field(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<&str>, StructureError> {
+        match self.fields.get(field_idx as usize) {
+            Some(&field) => self.view.seek_field(record_idx, field as u32),
+            None => Ok(None),
+        }
+    }
+    /// The field without the enclosing quotes, with the escapes resolved, and transcoded to
+    /// UTF-8 (see `RecordSource::seek_field_value`)
+    pub fn seek_field_value(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> Result<Option<Cow<'_, str>>, StructureError> {
+        match self.fields.get(field_idx as usize) {
+            Some(&field) => {
+                self.view.seek_field_value(record_idx, field as u32)
+            }
+            None => Ok(None),
+        }
+    }
+    /// The field without bounds or UTF-8 checks.
+    ///
+    /// # Safety
+    ///
+    /// As `RecordSource::seek_field_unchecked`; the positions are those of the projection.
+    pub unsafe fn seek_field_unchecked(
+        &self,
+        record_idx: u32,
+        field_idx: u32,
+    ) -> &str {
+        let field = *self.fields.get_unchecked(field_idx as usize);
+        self.view.seek_field_unchecked(record_idx, field as u32)
+    }
+    /// The fields in view joined by the delimiter; a copy, validated as UTF-8.
+    pub fn seek_record(
+        &self,
+        record_idx: u32,
+    ) -> Result<Option<String>, StructureError> {
+        if record_idx as usize >= self.len() {
+            return Ok(None);
+        }
+        let delimiter = self.view.dialect().delimiter as char;
+        let mut record = String::new();
+        for field_idx in 0..self.field_cnt() {
+            if field_idx > 0 {
+                record.push(delimiter);
+            }
+            let field = self
+                .seek_field(record_idx, field_idx)?
+                .ok_or(StructureError::InvalidState)?;
+            record.push_str(field);
+        }
+        Ok(Some(record))
+    }
+    /// The bytes shared with the Tape
+    pub fn data_bytes(&self) -> &[u8] {
+        self.view.data_bytes()
+    }
+    /// Tape field -> projection field
+    fn position(
+        &self,
+        field: usize,
+        name: &str,
+    ) -> Result<usize, StructureError> {
+        self.fields.iter().position(|&f| f == field).ok_or_else(|| {
+            let names = self
+                .header()
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            StructureError::UnknownField {
+                name: name.to_string(),
+                near: HeaderMap::new(&names, NameMatching::exact()).near(name),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{create, Encoding, RecordSource, StructureError};
+
+    #[test]
+    fn slice_and_project() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let source = &tape;
+
+        let slice = tape.slice(2..5).unwrap();
+        assert_eq!(slice.len(), 3);
+        assert_eq!(slice.record_cnt(), Some(4));
+        assert_eq!(
+            slice.seek_record(0).unwrap(),
+            source.seek_record(2).unwrap()
+        );
+        assert_eq!(slice.seek_field(3, 0).unwrap(), None);
+        assert!(tape.slice(5..9).is_none());
+
+        let projected = tape.project(&["NRx Count", "NPI Number"]).unwrap();
+        assert_eq!(projected.header(), vec!["NRx Count", "NPI Number"]);
+        assert_eq!(projected.field_cnt(), 2);
+        assert_eq!(
+            projected.seek_field(1, 0).unwrap(),
+            source.seek_field(1, 7).unwrap()
+        );
+        assert_eq!(projected.seek_field(1, 2).unwrap(), None);
+
+        let both = projected.slice(6..7).unwrap();
+        assert_eq!(both.seek_field(0, 1).unwrap(), Some("1003002819"));
+        assert_eq!(both.field_index("NPI Number").unwrap(), 1);
+        let narrowed = both.project(&["NPI Number"]).unwrap();
+        assert_eq!(narrowed.fields(), &[0]);
+        assert!(matches!(
+            narrowed.project(&["NRx Count"]),
+            Err(StructureError::UnknownField { .. })
+        ));
+        // shares the data
+        assert_eq!(narrowed.data_bytes().as_ptr(), tape.bytes().as_ptr());
+        let nrx = both.seek_field(0, 0).unwrap().unwrap();
+        assert_eq!(
+            both.seek_record(0).unwrap(),
+            Some(format!("{},1003002819", nrx))
+        );
+        assert_eq!(both.seek_record(1).unwrap(), None);
+        assert_eq!(
+            both.view().seek_record(0).unwrap(),
+            source.seek_record(6).unwrap()
+        );
+    }
+    #[test]
+    fn unchecked_matches_checked() {
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let slice = tape.slice(1..3).unwrap();
+        let view = slice.project(&["Year-Month", "NPI Number"]).unwrap();
+        for record in 0..view.len() as u32 {
+            for field in 0..view.field_cnt() {
+                let checked = view.seek_field(record, field).unwrap();
+                let unchecked =
+                    unsafe { view.seek_field_unchecked(record, field) };
+                assert_eq!(checked, Some(unchecked));
+            }
+            let checked = slice.seek_record(record).unwrap();
+            let unchecked = unsafe { slice.seek_record_unchecked(record) };
+            assert_eq!(checked, Some(unchecked));
+        }
+        assert_eq!(unsafe { view.seek_field_unchecked(1, 0) }, "2015-03");
+    }
+}
@@ -0,0 +1,283 @@
+///
+/// Push-based (SAX-like) reading of the structure
+///
+/// The events are driven straight from the Stage1 bitmaps of each block of 64 bytes; the
+/// positions are consumed as they are found.  Neither a `StructureIndex`, nor a Tape is built.
+///
+/// 🔑 Memory is constant; a file of any size is read in a single pass (memory map the file).
+///
+/// The records are numbered from zero; the first record is the header (if any).  The number of
+/// fields of the first record is the number expected of the others.
+///
+use std::fs::File;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use memmap::Mmap;
+
+use crate::builder::simd_available;
+use crate::dialect::{Dialect, Escape};
+use crate::encoding::UTF8_BOM;
+use crate::error::StructureError;
+use crate::reader;
+use crate::scalar;
+
+/// The receiver of the structural events
+pub trait Visitor {
+    /// The raw bytes of a field (see `Dialect::unescape` for the value)
+    fn on_field(&mut self, record: u32, field: u32, bytes: &[u8]);
+    fn on_record_end(&mut self, _record: u32) {}
+    /// Ok to continue; Err to stop the read and report the error.  Stops by default.
+    fn on_error(
+        &mut self,
+        error: StructureError,
+    ) -> Result<(), StructureError> {
+        Err(error)
+    }
+}
+
+/// Visit the fields of the bytes; returns the number of records (includes the header).
+pub fn visit<V: Visitor>(
+    bytes: &[u8],
+    dialect: &Dialect,
+    visitor: &mut V,
+) -> Result<u32, StructureError> {
+    let mut events = Events {
+        bytes,
+        visitor,
+        field_start: if bytes.starts_with(UTF8_BOM) {
+            UTF8_BOM.len()
+        } else {
+            0
+        },
+        last_cr: None,
+        record: 0,
+        field: 0,
+        expected: None,
+        error: None,
+    };
+    let on_block = |mut set_bits: u64, codepoint_cnt: usize| {
+        while set_bits != 0 {
+            let pos = codepoint_cnt + set_bits.trailing_zeros() as usize;
+            events.structure(pos, dialect.delimiter)?;
+            set_bits &= set_bits - 1;
+        }
+        ControlFlow::Continue(())
+    };
+    match dialect.escape {
+        Escape::Doubled if simd_available() => {
+            reader::for_each_block(bytes, dialect, on_block)
+        }
+        _ => scalar::for_each_block(bytes, dialect, on_block),
+    }
+    events.finish()
+}
+
+/// `visit` a memory map of the file
+pub fn visit_path<V: Visitor>(
+    path: impl AsRef<Path>,
+    dialect: &Dialect,
+    visitor: &mut V,
+) -> Result<u32, StructureError> {
+    let file = File::open(path)?;
+    let memmap = unsafe { Mmap::map(&file)? };
+    visit(&memmap, dialect, visitor)
+}
+
+/// The state between two structural positions
+struct Events<'a, V> {
+    bytes: &'a [u8],
+    visitor: &'a mut V,
+    field_start: usize,
+    /// the LF of a CRLF is not a record
+    last_cr: Option<usize>,
+    record: u32,
+    field: u32,
+    expected: Option<u32>,
+    error: Option<StructureError>,
+}
+
+impl<'a, V: Visitor> Events<'a, V> {
+    fn structure(&mut self, pos: usize, delimiter: u8) -> ControlFlow<()> {
+        match self.bytes[pos] {
+            b'\n' if self.last_cr.map(|cr| cr + 1) == Some(pos) => {
+                self.field_start = pos + 1;
+            }
+            byte @ b'\r' | byte @ b'\n' => {
+                self.emit_field(pos);
+                self.end_record()?;
+                self.last_cr = (byte == b'\r').then_some(pos);
+            }
+            byte if byte == delimiter => {
+                self.emit_field(pos);
+                self.field += 1;
+            }
+            _ => (),
+        }
+        ControlFlow::Continue(())
+    }
+    fn emit_field(&mut self, end: usize) {
+        self.visitor.on_field(
+            self.record,
+            self.field,
+            &self.bytes[self.field_start..end],
+        );
+        self.field_start = end + 1;
+    }
+    fn end_record(&mut self) -> ControlFlow<()> {
+        let field_cnt = self.field + 1;
+        match self.expected {
+            None => self.expected = Some(field_cnt),
+            Some(expected) if expected != field_cnt => {
+                let error = StructureError::RaggedRecord {
+                    record: self.record,
+                };
+                if let Err(error) = self.visitor.on_error(error) {
+                    self.error = Some(error);
+                    return ControlFlow::Break(());
+                }
+            }
+            _ => (),
+        }
+        self.visitor.on_record_end(self.record);
+        self.record += 1;
+        self.field = 0;
+        ControlFlow::Continue(())
+    }
+    /// The last record may not have a terminator; it has started with a byte or a delimiter
+    fn finish(mut self) -> Result<u32, StructureError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        if self.field_start < self.bytes.len() || self.field > 0 {
+            self.emit_field(self.bytes.len());
+            if self.end_record().is_break() {
+                return Err(self.error.take().unwrap_or(
+                    StructureError::RaggedRecord {
+                        record: self.record,
+                    },
+                ));
+            }
+        }
+        Ok(self.record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create, Encoding};
+
+    /// Sum of a column, and a count of the fields
+    #[derive(Default)]
+    struct Sum {
+        column: u32,
+        total: u64,
+        fields: usize,
+        records: Vec<u32>,
+        errors: Vec<u32>,
+    }
+    impl Visitor for Sum {
+        fn on_field(&mut self, record: u32, field: u32, bytes: &[u8]) {
+            self.fields += 1;
+            if record > 0 && field == self.column {
+                let value = std::str::from_utf8(bytes).unwrap();
+                self.total += value.parse::<u64>().unwrap_or(0);
+            }
+        }
+        fn on_record_end(&mut self, record: u32) {
+            self.records.push(record);
+        }
+        fn on_error(
+            &mut self,
+            error: StructureError,
+        ) -> Result<(), StructureError> {
+            match error {
+                StructureError::RaggedRecord { record } => {
+                    self.errors.push(record);
+                    Ok(())
+                }
+                error => Err(error),
+            }
+        }
+    }
+
+    #[test]
+    fn aggregate() {
+        let mut sum = Sum {
+            column: 7,
+            ..Sum::default()
+        };
+        let cnt =
+            visit_path("./res/sample_rx.csv", &Dialect::default(), &mut sum)
+                .unwrap();
+        let tape = create("./res/sample_rx.csv", Encoding::Utf8).unwrap();
+        let expected = tape
+            .column("NRx Count")
+            .unwrap()
+            .values()
+            .map(|value| value.parse::<u64>().unwrap())
+            .sum::<u64>();
+        assert_eq!(cnt, tape.record_cnt);
+        assert_eq!(sum.total, expected);
+        assert_eq!(sum.fields, 8 * cnt as usize);
+        assert!(sum.errors.is_empty());
+    }
+    #[test]
+    fn ragged_and_unterminated() {
+        let mut sum = Sum::default();
+        let cnt =
+            visit(b"a,b\n1,2\n3\n4,5", &Dialect::default(), &mut sum).unwrap();
+        assert_eq!(cnt, 4);
+        assert_eq!(sum.errors, vec![2]);
+        assert_eq!(sum.records, vec![0, 1, 2, 3]);
+        assert_eq!(sum.total, 1 + 3 + 4);
+
+        #[derive(Default)]
+        struct Fields(Vec<(u32, u32, Vec<u8>)>, Vec<u32>);
+        impl Visitor for Fields {
+            fn on_field(&mut self, record: u32, field: u32, bytes: &[u8]) {
+                self.0.push((record, field, bytes.to_vec()));
+            }
+            fn on_record_end(&mut self, record: u32) {
+                self.1.push(record);
+            }
+        }
+        let mut fields = Fields::default();
+        let cnt = visit(b"a,b\n1,", &Dialect::default(), &mut fields).unwrap();
+        assert_eq!(cnt, 2);
+        assert_eq!(fields.0[2..], [(1, 0, b"1".to_vec()), (1, 1, Vec::new())]);
+        assert_eq!(fields.1, vec![0, 1]);
+        let mut fields = Fields::default();
+        let cnt = visit(b"a,b\r\n", &Dialect::default(), &mut fields).unwrap();
+        assert_eq!(cnt, 1);
+        assert_eq!(fields.1, vec![0]);
+
+        /// Stops on the first error
+        struct Strict;
+        impl Visitor for Strict {
+            fn on_field(&mut self, _: u32, _: u32, _: &[u8]) {}
+        }
+        assert!(matches!(
+            visit(b"a,b\n1\n", &Dialect::default(), &mut Strict),
+            Err(StructureError::RaggedRecord { record: 1 })
+        ));
+    }
+    #[test]
+    fn backslash_escape() {
+        #[derive(Default)]
+        struct Fields(Vec<Vec<u8>>);
+        impl Visitor for Fields {
+            fn on_field(&mut self, _: u32, _: u32, bytes: &[u8]) {
+                self.0.push(bytes.to_vec());
+            }
+        }
+        let dialect = Dialect {
+            escape: Escape::Backslash(b'\\'),
+            ..Dialect::default()
+        };
+        let mut fields = Fields::default();
+        visit(b"a,\"b\\\",c\"\r\n", &dialect, &mut fields).unwrap();
+        assert_eq!(fields.0, vec![&b"a"[..], b"\"b\\\",c\""]);
+    }
+}